@@ -2,6 +2,9 @@ use std::path::Path;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let schemas = Path::new("schemas");
-    vbare_compiler::process_schemas(schemas)?;
+    let config = vbare_compiler::Config::default()
+        .with_versioned_type("App")
+        .with_context("App", "crate::Migration");
+    vbare_compiler::process_schemas_with_config(schemas, &config)?;
     Ok(())
 }
@@ -1,6 +1,5 @@
-use anyhow::{bail, Result};
-use serde::{Deserialize, Serialize};
-use vbare::OwnedVersionedData;
+use anyhow::Result;
+use vbare::{ContextOwnedVersionedData, DowngradeReport};
 
 // Bring generated schemas into this crate
 pub mod schemas {
@@ -8,212 +7,200 @@ pub mod schemas {
     include!(concat!(env!("OUT_DIR"), "/combined_imports.rs"));
 }
 
-// A simple versioned wrapper for App across v1, v2, v3.
-#[derive(Clone)]
-pub enum AppVersioned {
-    V1(schemas::v1::App),
-    V2(schemas::v2::App),
-    V3(schemas::v3::App),
+// A simple versioned wrapper for App across v1, v2, v3, generated by build.rs.
+pub use schemas::AppVersioned;
+
+/// State the migrations need that is not in the payload.
+pub struct Migration {
+    /// Timestamp given to todos that did not record one.
+    pub now: u64,
+    /// Id given to the next tag created from a v2 tag name.
+    pub next_tag_id: schemas::v3::TagId,
+    /// Data the downgrades could not represent in the older version.
+    pub report: DowngradeReport,
 }
 
-impl vbare::OwnedVersionedData for AppVersioned {
-    type Latest = schemas::v3::App;
-
-    fn wrap_latest(latest: Self::Latest) -> Self {
-        AppVersioned::V3(latest)
-    }
-
-    fn unwrap_latest(self) -> Result<Self::Latest> {
-        match self {
-            AppVersioned::V3(app) => Ok(app),
-            _ => bail!("version not latest"),
-        }
-    }
-
-    fn deserialize_version(payload: &[u8], version: u16) -> Result<Self> {
-        match version {
-            1 => Ok(AppVersioned::V1(serde_bare::from_slice(payload)?)),
-            2 => Ok(AppVersioned::V2(serde_bare::from_slice(payload)?)),
-            3 => Ok(AppVersioned::V3(serde_bare::from_slice(payload)?)),
-            _ => bail!("invalid version: {version}"),
-        }
-    }
-
-    fn serialize_version(self, _version: u16) -> Result<Vec<u8>> {
-        match self {
-            AppVersioned::V1(data) => Ok(serde_bare::to_vec(&data)?),
-            AppVersioned::V2(data) => Ok(serde_bare::to_vec(&data)?),
-            AppVersioned::V3(data) => Ok(serde_bare::to_vec(&data)?),
+impl Default for Migration {
+    fn default() -> Self {
+        Self {
+            now: 0,
+            next_tag_id: 1,
+            report: DowngradeReport::new(),
         }
     }
+}
 
-    fn deserialize_converters() -> Vec<impl Fn(Self) -> Result<Self>> {
-        vec![Self::v1_to_v2, Self::v2_to_v3]
-    }
-
-    fn serialize_converters() -> Vec<impl Fn(Self) -> Result<Self>> {
-        // Not used by this example, but keep symmetric
-        vec![Self::v3_to_v2, Self::v2_to_v1]
+impl AsMut<DowngradeReport> for Migration {
+    fn as_mut(&mut self) -> &mut DowngradeReport {
+        &mut self.report
     }
 }
 
-impl AppVersioned {
-    fn v1_to_v2(self) -> Result<Self> {
-        use schemas::{v1, v2};
-        match self {
-            AppVersioned::V1(app) => {
-                let mut todos: std::collections::HashMap<v2::TodoId, v2::Todo> = Default::default();
-                for t in app.todos.into_iter() {
-                    let id: v2::TodoId = t.id as u64;
-                    let status = if t.done {
-                        v2::TodoStatus::Done
-                    } else {
-                        v2::TodoStatus::Open
-                    };
-                    let todo = v2::Todo {
-                        id,
-                        title: t.title,
-                        status,
-                        created_at: 0,
-                        tags: Vec::new(),
-                    };
-                    todos.insert(id, todo);
-                }
-                let app_v2 = v2::App {
-                    todos,
-                    settings: Default::default(),
-                };
-                Ok(AppVersioned::V2(app_v2))
-            }
-            other => Ok(other),
+impl schemas::AppVersionedConverters for AppVersioned {
+    fn v1_to_v2(app: schemas::v1::App, ctx: &mut Migration) -> Result<schemas::v2::App> {
+        use schemas::v2;
+        let mut todos: std::collections::HashMap<v2::TodoId, v2::Todo> = Default::default();
+        for t in app.todos.into_iter() {
+            let id: v2::TodoId = t.id as u64;
+            let status = if t.done {
+                v2::TodoStatus::Done
+            } else {
+                v2::TodoStatus::Open
+            };
+            let todo = v2::Todo {
+                id,
+                title: t.title,
+                status,
+                created_at: ctx.now,
+                tags: Vec::new(),
+            };
+            todos.insert(id, todo);
         }
+        Ok(v2::App {
+            todos,
+            settings: Default::default(),
+        })
     }
 
-    fn v2_to_v3(self) -> Result<Self> {
+    fn v2_to_v3(app: schemas::v2::App, ctx: &mut Migration) -> Result<schemas::v3::App> {
         use schemas::{v2, v3};
-        match self {
-            AppVersioned::V2(app) => {
-                // Convert tags: Vec<String> -> HashMap<TagId, Tag>
-                fn convert_tags(
-                    tags: Vec<String>,
-                ) -> std::collections::HashMap<v3::TagId, v3::Tag> {
-                    let mut map = std::collections::HashMap::new();
-                    let mut next_id: v3::TagId = 1; // simple incremental
-                    for name in tags.into_iter() {
-                        let tag = v3::Tag {
-                            id: next_id,
-                            name,
-                            color: None,
-                        };
-                        map.insert(next_id, tag);
-                        next_id += 1;
-                    }
-                    map
-                }
-
-                let mut todos: std::collections::HashMap<v3::TodoId, v3::Todo> = Default::default();
-                for (id, t) in app.todos.into_iter() {
-                    let detail = v3::TodoDetail {
-                        title: t.title,
-                        tags: convert_tags(t.tags),
-                    };
-                    let status = match t.status {
-                        v2::TodoStatus::Open => v3::TodoStatus::Open,
-                        v2::TodoStatus::InProgress => v3::TodoStatus::InProgress,
-                        v2::TodoStatus::Done => v3::TodoStatus::Done,
-                    };
-                    let todo = v3::Todo {
-                        id,
-                        status,
-                        created_at: t.created_at,
-                        priority: v3::Priority::Low,
-                        assignee: v3::Assignee {
-                            kind: v3::AssigneeKind::None,
-                            user_id: None,
-                            team_id: None,
-                        },
-                        detail,
-                        history: Vec::new(),
-                    };
-                    todos.insert(id, todo);
-                }
-
-                let app_v3 = v3::App {
-                    todos,
-                    config: v3::AppConfig {
-                        theme: v3::Theme::System,
-                        features: Default::default(),
-                    },
-                    boards: Default::default(),
+        // Convert tags: Vec<String> -> HashMap<TagId, Tag>
+        fn convert_tags(
+            tags: Vec<String>,
+            ctx: &mut Migration,
+        ) -> std::collections::HashMap<v3::TagId, v3::Tag> {
+            let mut map = std::collections::HashMap::new();
+            for name in tags.into_iter() {
+                let id = ctx.next_tag_id;
+                ctx.next_tag_id += 1;
+                let tag = v3::Tag {
+                    id,
+                    name,
+                    color: None,
                 };
-                Ok(AppVersioned::V3(app_v3))
+                map.insert(id, tag);
             }
-            other => Ok(other),
+            map
+        }
+
+        let mut todos: std::collections::HashMap<v3::TodoId, v3::Todo> = Default::default();
+        for (id, t) in app.todos.into_iter() {
+            let detail = v3::TodoDetail {
+                title: t.title,
+                tags: convert_tags(t.tags, ctx),
+            };
+            let status = match t.status {
+                v2::TodoStatus::Open => v3::TodoStatus::Open,
+                v2::TodoStatus::InProgress => v3::TodoStatus::InProgress,
+                v2::TodoStatus::Done => v3::TodoStatus::Done,
+            };
+            let todo = v3::Todo {
+                id,
+                status,
+                created_at: t.created_at,
+                priority: v3::Priority::Low,
+                assignee: v3::Assignee {
+                    kind: v3::AssigneeKind::None,
+                    user_id: None,
+                    team_id: None,
+                },
+                detail,
+                history: Vec::new(),
+            };
+            todos.insert(id, todo);
         }
+
+        Ok(v3::App {
+            todos,
+            config: v3::AppConfig {
+                theme: v3::Theme::System,
+                features: Default::default(),
+            },
+            boards: Default::default(),
+        })
     }
 
-    fn v3_to_v2(self) -> Result<Self> {
+    fn v3_to_v2(app: schemas::v3::App, ctx: &mut Migration) -> Result<schemas::v2::App> {
         use schemas::{v2, v3};
-        match self {
-            AppVersioned::V3(app) => {
-                fn revert_tags(tags: std::collections::HashMap<v3::TagId, v3::Tag>) -> Vec<String> {
-                    let mut v = Vec::with_capacity(tags.len());
-                    for (_, tag) in tags.into_iter() {
-                        v.push(tag.name);
-                    }
-                    v
-                }
-                let mut todos: std::collections::HashMap<v2::TodoId, v2::Todo> = Default::default();
-                for (id, t) in app.todos.into_iter() {
-                    let title = t.detail.title;
-                    let tags = revert_tags(t.detail.tags);
-                    let status = match t.status {
-                        v3::TodoStatus::Open => v2::TodoStatus::Open,
-                        v3::TodoStatus::InProgress => v2::TodoStatus::InProgress,
-                        v3::TodoStatus::Done => v2::TodoStatus::Done,
-                    };
-                    todos.insert(
-                        id,
-                        v2::Todo {
-                            id,
-                            title,
-                            status,
-                            created_at: t.created_at,
-                            tags,
-                        },
+        fn revert_tags(tags: std::collections::HashMap<v3::TagId, v3::Tag>) -> Vec<String> {
+            let mut v = Vec::with_capacity(tags.len());
+            for (_, tag) in tags.into_iter() {
+                v.push(tag.name);
+            }
+            v
+        }
+        let mut todos: std::collections::HashMap<v2::TodoId, v2::Todo> = Default::default();
+        for (id, t) in app.todos.into_iter() {
+            if !matches!(t.priority, v3::Priority::Low) {
+                ctx.report
+                    .record(format!("todos[{id}].priority"), "v2 has no priority");
+            }
+            if !matches!(t.assignee.kind, v3::AssigneeKind::None) {
+                ctx.report
+                    .record(format!("todos[{id}].assignee"), "v2 has no assignee");
+            }
+            if !t.history.is_empty() {
+                ctx.report
+                    .record(format!("todos[{id}].history"), "v2 has no history");
+            }
+            for (tag_id, tag) in &t.detail.tags {
+                if tag.color.is_some() {
+                    ctx.report.record(
+                        format!("todos[{id}].detail.tags[{tag_id}].color"),
+                        "v2 tags are names only",
                     );
                 }
-                let app_v2 = v2::App {
-                    todos,
-                    settings: Default::default(),
-                };
-                Ok(AppVersioned::V2(app_v2))
             }
-            other => Ok(other),
+            let title = t.detail.title;
+            let tags = revert_tags(t.detail.tags);
+            let status = match t.status {
+                v3::TodoStatus::Open => v2::TodoStatus::Open,
+                v3::TodoStatus::InProgress => v2::TodoStatus::InProgress,
+                v3::TodoStatus::Done => v2::TodoStatus::Done,
+            };
+            todos.insert(
+                id,
+                v2::Todo {
+                    id,
+                    title,
+                    status,
+                    created_at: t.created_at,
+                    tags,
+                },
+            );
         }
+        if !matches!(app.config.theme, v3::Theme::System) || !app.config.features.is_empty() {
+            ctx.report.record("config", "v2 has no app config");
+        }
+        if !app.boards.is_empty() {
+            ctx.report.record("boards", "v2 has no boards");
+        }
+        Ok(v2::App {
+            todos,
+            settings: Default::default(),
+        })
     }
 
-    fn v2_to_v1(self) -> Result<Self> {
+    fn v2_to_v1(app: schemas::v2::App, _ctx: &mut Migration) -> Result<schemas::v1::App> {
         use schemas::{v1, v2};
-        match self {
-            AppVersioned::V2(app) => {
-                let mut todos: Vec<v1::Todo> = Vec::new();
-                for (_id, t) in app.todos.into_iter() {
-                    let done = matches!(t.status, v2::TodoStatus::Done);
-                    todos.push(v1::Todo {
-                        id: t.id as u32,
-                        title: t.title,
-                        done,
-                    });
-                }
-                Ok(AppVersioned::V1(v1::App { todos }))
-            }
-            other => Ok(other),
+        let mut todos: Vec<v1::Todo> = Vec::new();
+        for (_id, t) in app.todos.into_iter() {
+            let done = matches!(t.status, v2::TodoStatus::Done);
+            todos.push(v1::Todo {
+                id: t.id as u32,
+                title: t.title,
+                done,
+            });
         }
+        Ok(v1::App { todos })
     }
 }
 
 // Convenience function for consumers/tests
-pub fn migrate_to_latest(payload: &[u8], version: u16) -> Result<schemas::v3::App> {
-    AppVersioned::deserialize(payload, version)
+pub fn migrate_to_latest(
+    payload: &[u8],
+    version: u16,
+    ctx: &mut Migration,
+) -> vbare::Result<schemas::v3::App> {
+    AppVersioned::deserialize_with_context(payload, version, ctx)
 }
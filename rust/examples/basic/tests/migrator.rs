@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
-use basic::{schemas, AppVersioned};
-use vbare::OwnedVersionedData;
+use basic::{schemas, AppVersioned, Migration};
+use vbare::{ContextOwnedVersionedData, OwnedVersionedData, ReportingOwnedVersionedData};
 
 #[test]
 fn migrates_v1_to_v3() {
@@ -21,11 +21,39 @@ fn migrates_v1_to_v3() {
     };
 
     let bytes = serde_bare::to_vec(&app_v1).unwrap();
-    let migrated = AppVersioned::deserialize(&bytes, 1).unwrap();
+    let mut ctx = Migration {
+        now: 1_700_000_000,
+        ..Default::default()
+    };
+    let migrated = basic::migrate_to_latest(&bytes, 1, &mut ctx).unwrap();
 
     assert_eq!(migrated.todos.len(), 2);
     let done = migrated.todos.get(&2).unwrap();
     assert!(matches!(done.status, schemas::v3::TodoStatus::Done));
+    assert_eq!(done.created_at, 1_700_000_000);
+}
+
+#[test]
+fn migrates_without_context() {
+    let app_v1 = schemas::v1::App {
+        todos: vec![schemas::v1::Todo {
+            id: 1,
+            title: "a".into(),
+            done: true,
+        }],
+    };
+    let bytes = serde_bare::to_vec(&app_v1).unwrap();
+
+    // The plain path runs the same converters with `Migration::default()`
+    let migrated = AppVersioned::deserialize(&bytes, 1).unwrap();
+    let todo = migrated.todos.get(&1).unwrap();
+    assert!(matches!(todo.status, schemas::v3::TodoStatus::Done));
+    assert_eq!(todo.created_at, 0);
+
+    let bytes = AppVersioned::wrap_latest(migrated).serialize(1).unwrap();
+    let app_v1: schemas::v1::App = serde_bare::from_slice(&bytes).unwrap();
+    assert_eq!(app_v1.todos[0].title, "a");
+    assert!(app_v1.todos[0].done);
 }
 
 #[test]
@@ -46,12 +74,18 @@ fn migrates_v2_to_v3_with_tags() {
         settings: HashMap::new(),
     };
     let bytes = serde_bare::to_vec(&app_v2).unwrap();
-    let migrated = AppVersioned::deserialize(&bytes, 2).unwrap();
+    let mut ctx = Migration {
+        next_tag_id: 100,
+        ..Default::default()
+    };
+    let migrated = AppVersioned::deserialize_with_context(&bytes, 2, &mut ctx).unwrap();
 
     let t = migrated.todos.get(&5).unwrap();
     assert_eq!(t.detail.title, "with-tags");
-    assert_eq!(t.detail.tags.len(), 2);
+    assert_eq!(t.detail.tags[&100].name, "red");
+    assert_eq!(t.detail.tags[&101].name, "blue");
     assert_eq!(t.created_at, 42);
+    assert_eq!(ctx.next_tag_id, 102);
 }
 
 #[test]
@@ -88,7 +122,9 @@ fn serializes_v3_to_v1() {
     };
 
     // Serialize to version 1 using the migrator's serialize path
-    let bytes = AppVersioned::V3(app_v3).serialize(1).unwrap();
+    let bytes = AppVersioned::V3(app_v3)
+        .serialize_with_context(1, &mut Migration::default())
+        .unwrap();
 
     // Decode as v1::App and assert down-conversion
     let app_v1: schemas::v1::App = serde_bare::from_slice(&bytes).unwrap();
@@ -98,3 +134,51 @@ fn serializes_v3_to_v1() {
     assert_eq!(t.title, "hello");
     assert!(t.done);
 }
+
+#[test]
+fn reports_losses_from_v3_to_v2() {
+    let mut todos: HashMap<schemas::v3::TodoId, schemas::v3::Todo> = HashMap::new();
+    todos.insert(
+        7,
+        schemas::v3::Todo {
+            id: 7,
+            status: schemas::v3::TodoStatus::Open,
+            created_at: 123,
+            priority: schemas::v3::Priority::High,
+            assignee: schemas::v3::Assignee {
+                kind: schemas::v3::AssigneeKind::None,
+                user_id: None,
+                team_id: None,
+            },
+            detail: schemas::v3::TodoDetail {
+                title: "hello".into(),
+                tags: HashMap::new(),
+            },
+            history: Vec::new(),
+        },
+    );
+    let app_v3 = schemas::v3::App {
+        todos,
+        config: schemas::v3::AppConfig {
+            theme: schemas::v3::Theme::System,
+            features: HashMap::new(),
+        },
+        boards: HashMap::new(),
+    };
+
+    let mut ctx = Migration::default();
+    let (bytes, report) = AppVersioned::V3(app_v3.clone())
+        .serialize_with_report(2, &mut ctx)
+        .unwrap();
+    let app_v2: schemas::v2::App = serde_bare::from_slice(&bytes).unwrap();
+    assert_eq!(app_v2.todos[&7].title, "hello");
+
+    let paths: Vec<_> = report.losses().iter().map(|loss| &*loss.path).collect();
+    assert_eq!(paths, ["todos[7].priority"]);
+    assert_eq!((report.losses()[0].from, report.losses()[0].to), (3, 2));
+
+    assert!(matches!(
+        AppVersioned::V3(app_v3).serialize_strict(2, &mut ctx),
+        Err(vbare::Error::LossyDowngrade(_))
+    ));
+}
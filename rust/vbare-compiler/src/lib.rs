@@ -1,5 +1,5 @@
 use indoc::formatdoc;
-use std::{fs, path::Path};
+use std::{collections::BTreeMap, fs, path::Path};
 
 /// Configuration for the vbare-compiler.
 ///
@@ -8,12 +8,32 @@ use std::{fs, path::Path};
 pub struct Config {
     /// Configuration forwarded to `vbare_gen` for schema codegen.
     pub vbare: vbare_gen::Config,
+    /// Root types to generate a `{Name}Versioned` enum and a `{Name}VersionedConverters` trait for.
+    /// Every type listed must exist in every `vN.bare` file.
+    ///
+    /// The enum derives `vbare::OwnedVersionedData`, so the `derive` feature of `vbare` must be
+    /// enabled.
+    pub versioned_types: Vec<String>,
+    /// Oldest version the `OwnedVersionedData` impl of each versioned type accepts, keyed by type
+    /// name. Converter functions to and from older versions are left out of its converter trait.
+    /// Types not listed accept every version.
+    pub min_supported_versions: BTreeMap<String, u16>,
+    /// Context type passed to the converter functions of each versioned type, keyed by type name.
+    /// The path must resolve from inside the generated module, such as `crate::Migration`.
+    ///
+    /// The converter functions of these types take a `&mut` context after the data and back a
+    /// `vbare::ContextOwnedVersionedData` impl. The context must implement `Default`, which the
+    /// converters of `OwnedVersionedData` pass to the same functions.
+    pub contexts: BTreeMap<String, String>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             vbare: vbare_gen::Config::with_hash_map(),
+            versioned_types: Vec::new(),
+            min_supported_versions: BTreeMap::new(),
+            contexts: BTreeMap::new(),
         }
     }
 }
@@ -23,6 +43,7 @@ impl Config {
     pub fn with_hashable_map() -> Self {
         Self {
             vbare: vbare_gen::Config::with_hashable_map(),
+            ..Default::default()
         }
     }
 
@@ -30,8 +51,27 @@ impl Config {
     pub fn with_hash_map() -> Self {
         Self {
             vbare: vbare_gen::Config::with_hash_map(),
+            ..Default::default()
         }
     }
+
+    /// Generates a versioned wrapper for the given root type. See `Config::versioned_types`.
+    pub fn with_versioned_type(mut self, name: impl Into<String>) -> Self {
+        self.versioned_types.push(name.into());
+        self
+    }
+
+    /// Sets the oldest version a versioned type accepts. See `Config::min_supported_versions`.
+    pub fn with_min_supported_version(mut self, name: impl Into<String>, version: u16) -> Self {
+        self.min_supported_versions.insert(name.into(), version);
+        self
+    }
+
+    /// Passes a context to the converters of a versioned type. See `Config::contexts`.
+    pub fn with_context(mut self, name: impl Into<String>, context: impl Into<String>) -> Self {
+        self.contexts.insert(name.into(), context.into());
+        self
+    }
 }
 
 /// Process BARE schema files and generate Rust code.
@@ -50,6 +90,7 @@ pub fn process_schemas_with_config(
     println!("cargo:rerun-if-changed={}", schema_dir.display());
 
     let mut all_names = Vec::new();
+    let mut versions = BTreeMap::new();
 
     for entry in fs::read_dir(schema_dir)?.flatten() {
         let path = entry.path();
@@ -68,9 +109,16 @@ pub fn process_schemas_with_config(
             .0;
 
         let tokens = vbare_gen::bare_schema(&path, config.vbare);
-        let ast = syn::parse2(tokens)?;
+        let ast: syn::File = syn::parse2(tokens)?;
         let content = prettyplease::unparse(&ast);
 
+        if let Some(version) = bare_name
+            .strip_prefix('v')
+            .and_then(|v| v.parse::<u16>().ok())
+        {
+            versions.insert(version, ast);
+        }
+
         fs::write(out_path.join(format!("{bare_name}_generated.rs")), content)?;
 
         all_names.push(bare_name.to_string());
@@ -79,6 +127,14 @@ pub fn process_schemas_with_config(
     let mut mod_content = String::new();
     mod_content.push_str("// Auto-generated module file for schemas\n\n");
 
+    if !config.versioned_types.is_empty() {
+        let content = versioned_types(config, &versions)?;
+        fs::write(out_path.join("versioned_generated.rs"), content)?;
+
+        mod_content
+            .push_str("include!(concat!(env!(\"OUT_DIR\"), \"/versioned_generated.rs\"));\n\n");
+    }
+
     for name in all_names {
         let module_name = name.replace('.', "_");
         mod_content.push_str(&formatdoc!(
@@ -96,3 +152,148 @@ pub fn process_schemas_with_config(
 
     Ok(())
 }
+
+/// Generates the versioned enum and converter trait for each root type.
+///
+/// `versions` maps each version number to the parsed contents of its `vN.bare` file.
+fn versioned_types(
+    config: &Config,
+    versions: &BTreeMap<u16, syn::File>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let latest = versions.len() as u16;
+    if latest == 0 {
+        return Err("versioned types require schema files named v1.bare, v2.bare, ...".into());
+    }
+    for (i, version) in versions.keys().enumerate() {
+        if *version != i as u16 + 1 {
+            return Err(
+                format!("schema versions must be contiguous from v1, found v{version}").into(),
+            );
+        }
+    }
+
+    if let Some(name) = config
+        .min_supported_versions
+        .keys()
+        .find(|name| !config.versioned_types.contains(name))
+    {
+        return Err(
+            format!("minimum supported version set for {name}, which is not versioned").into(),
+        );
+    }
+    if let Some(name) = config
+        .contexts
+        .keys()
+        .find(|name| !config.versioned_types.contains(name))
+    {
+        return Err(format!("context set for {name}, which is not versioned").into());
+    }
+
+    let mut content = String::new();
+    for name in &config.versioned_types {
+        for (version, ast) in versions {
+            if !defines_type(ast, name) {
+                return Err(format!("versioned type {name} is missing from v{version}").into());
+            }
+        }
+
+        let min = config.min_supported_versions.get(name).copied();
+        if min.is_some_and(|min| min == 0 || min > latest) {
+            return Err(format!(
+                "minimum supported version of {name} must be between 1 and {latest}"
+            )
+            .into());
+        }
+
+        let context = config.contexts.get(name).map(String::as_str);
+        content.push_str(&versioned_type(name, latest, min, context));
+    }
+
+    let ast = syn::parse_file(&content)?;
+    Ok(prettyplease::unparse(&ast))
+}
+
+/// Emits the versioned enum with `#[derive(vbare::OwnedVersionedData)]`, wiring the typed steps of
+/// the derive to the functions of the converter trait. Steps to and from versions below `min` are
+/// left out, and the steps take `context` when one is given.
+fn versioned_type(name: &str, latest: u16, min: Option<u16>, context: Option<&str>) -> String {
+    let versioned = format!("{name}Versioned");
+    let converters = format!("{name}VersionedConverters");
+    let first = min.unwrap_or(1);
+    let ctx_arg = context
+        .map(|context| format!(", ctx: &mut {context}"))
+        .unwrap_or_default();
+
+    let mut variants = String::new();
+    for v in 1..=latest {
+        variants.push_str(&format!("V{v}(v{v}::{name}),\n"));
+    }
+
+    let mut trait_fns = String::new();
+    let mut upgrade = Vec::new();
+    let mut downgrade = Vec::new();
+    for v in first..latest {
+        let next = v + 1;
+        trait_fns.push_str(&format!(
+            "fn v{v}_to_v{next}(data: v{v}::{name}{ctx_arg}) -> ::vbare::__private::anyhow::Result<v{next}::{name}>;\n"
+        ));
+        upgrade.push(format!("Self::v{v}_to_v{next}"));
+    }
+    for v in (first + 1..=latest).rev() {
+        let prev = v - 1;
+        trait_fns.push_str(&format!(
+            "fn v{v}_to_v{prev}(data: v{v}::{name}{ctx_arg}) -> ::vbare::__private::anyhow::Result<v{prev}::{name}>;\n"
+        ));
+        downgrade.push(format!("Self::v{v}_to_v{prev}"));
+    }
+
+    let mut attrs = Vec::new();
+    if let Some(min) = min {
+        attrs.push(format!("min_supported_version = {min}"));
+    }
+    let (prefix, used_by) = match context {
+        Some(context) => {
+            attrs.push(format!("context = {context}"));
+            attrs.push("default_context".to_string());
+            ("context_", "vbare::ContextOwnedVersionedData")
+        }
+        None => ("", "vbare::OwnedVersionedData"),
+    };
+    if first < latest {
+        attrs.push(format!("{prefix}upgrade_steps = [{}]", upgrade.join(", ")));
+        attrs.push(format!(
+            "{prefix}downgrade_steps = [{}]",
+            downgrade.join(", ")
+        ));
+    }
+    let attrs = if attrs.is_empty() {
+        String::new()
+    } else {
+        format!("#[vbare({})]", attrs.join(", "))
+    };
+
+    formatdoc!(
+        r#"
+        #[derive(Clone, ::vbare::OwnedVersionedData)]
+        {attrs}
+        pub enum {versioned} {{
+            {variants}
+        }}
+
+        /// Conversions between adjacent versions of `{name}`, implemented by hand for
+        /// `{versioned}` and used by its `{used_by}` impl.
+        pub trait {converters} {{
+            {trait_fns}
+        }}
+        "#
+    )
+}
+
+fn defines_type(ast: &syn::File, name: &str) -> bool {
+    ast.items.iter().any(|item| match item {
+        syn::Item::Struct(x) => x.ident == name,
+        syn::Item::Enum(x) => x.ident == name,
+        syn::Item::Type(x) => x.ident == name,
+        _ => false,
+    })
+}
@@ -1,3 +1,9 @@
+#![allow(
+    clippy::manual_strip,
+    clippy::needless_range_loop,
+    clippy::unnecessary_unwrap
+)]
+
 use std::{env, fs, path::PathBuf};
 
 // Exercise process_schemas against the repo fixtures in fixtures/tests/basic
@@ -0,0 +1,146 @@
+use std::{env, fs, path::Path, sync::Mutex};
+
+// OUT_DIR is process-global, so tests that run the processor must not overlap.
+static OUT_DIR_LOCK: Mutex<()> = Mutex::new(());
+
+const V1: &str = "
+type Todo struct {
+  id: u32
+  title: str
+}
+type App struct {
+  todos: list<Todo>
+}
+";
+
+const V2: &str = "
+type Todo struct {
+  id: u64
+  title: str
+  done: bool
+}
+type App struct {
+  todos: list<Todo>
+}
+";
+
+fn process(schemas: &[(&str, &str)], config: &vbare_compiler::Config) -> Result<String, String> {
+    let _guard = OUT_DIR_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let out_dir = tempfile::tempdir().expect("create tempdir for OUT_DIR");
+    env::set_var("OUT_DIR", out_dir.path());
+
+    let schema_dir = tempfile::tempdir().expect("create tempdir for schema dir");
+    for (name, content) in schemas {
+        fs::write(schema_dir.path().join(format!("{name}.bare")), content).expect("write schema");
+    }
+
+    vbare_compiler::process_schemas_with_config(schema_dir.path(), config)
+        .map_err(|e| e.to_string())?;
+
+    Ok(read(out_dir.path(), "versioned_generated.rs")
+        + &read(out_dir.path(), "combined_imports.rs"))
+}
+
+fn read(dir: &Path, file: &str) -> String {
+    fs::read_to_string(dir.join(file)).unwrap_or_default()
+}
+
+#[test]
+fn generates_versioned_type() {
+    let config = vbare_compiler::Config::default().with_versioned_type("App");
+    let generated = process(&[("v1", V1), ("v2", V2)], &config).expect("process schemas");
+
+    assert!(generated.contains("pub enum AppVersioned {"));
+    assert!(generated.contains("V1(v1::App)"));
+    assert!(generated.contains("V2(v2::App)"));
+    assert!(generated.contains("pub trait AppVersionedConverters {"));
+    assert!(generated
+        .contains("fn v1_to_v2(data: v1::App) -> ::vbare::__private::anyhow::Result<v2::App>;"));
+    assert!(generated
+        .contains("fn v2_to_v1(data: v2::App) -> ::vbare::__private::anyhow::Result<v1::App>;"));
+    assert!(generated.contains("#[derive(Clone, ::vbare::OwnedVersionedData)]"));
+    assert!(generated.contains("upgrade_steps = [Self::v1_to_v2]"));
+    assert!(generated.contains("downgrade_steps = [Self::v2_to_v1]"));
+    assert!(generated.contains("/versioned_generated.rs"));
+}
+
+#[test]
+fn generates_min_supported_version() {
+    let config = vbare_compiler::Config::default()
+        .with_versioned_type("App")
+        .with_min_supported_version("App", 2);
+    let generated = process(&[("v1", V1), ("v2", V2)], &config).expect("process schemas");
+
+    assert!(generated.contains("#[vbare(min_supported_version = 2)]"));
+    assert!(generated.contains("V1(v1::App)"));
+    assert!(!generated.contains("fn v1_to_v2"));
+    assert!(!generated.contains("fn v2_to_v1"));
+
+    let config = vbare_compiler::Config::default()
+        .with_versioned_type("App")
+        .with_min_supported_version("App", 3);
+    let err = process(&[("v1", V1), ("v2", V2)], &config).unwrap_err();
+    assert_eq!(
+        err,
+        "minimum supported version of App must be between 1 and 2"
+    );
+
+    let config = vbare_compiler::Config::default()
+        .with_versioned_type("App")
+        .with_min_supported_version("Todo", 2);
+    let err = process(&[("v1", V1), ("v2", V2)], &config).unwrap_err();
+    assert_eq!(
+        err,
+        "minimum supported version set for Todo, which is not versioned"
+    );
+}
+
+#[test]
+fn generates_context() {
+    let config = vbare_compiler::Config::default()
+        .with_versioned_type("App")
+        .with_context("App", "crate::Migration");
+    let generated = process(&[("v1", V1), ("v2", V2)], &config).expect("process schemas");
+
+    assert!(generated.contains(
+        "fn v1_to_v2(\n        data: v1::App,\n        ctx: &mut crate::Migration,\n    )"
+    ));
+    assert!(generated.contains("context = crate::Migration"));
+    assert!(generated.contains("default_context"));
+    assert!(generated.contains("context_upgrade_steps = [Self::v1_to_v2]"));
+    assert!(generated.contains("context_downgrade_steps = [Self::v2_to_v1]"));
+    assert!(!generated.contains(" upgrade_steps"));
+
+    let config = vbare_compiler::Config::default()
+        .with_versioned_type("App")
+        .with_context("Todo", "crate::Migration");
+    let err = process(&[("v1", V1), ("v2", V2)], &config).unwrap_err();
+    assert_eq!(err, "context set for Todo, which is not versioned");
+}
+
+#[test]
+fn skips_versioned_types_by_default() {
+    let generated = process(
+        &[("v1", V1), ("v2", V2)],
+        &vbare_compiler::Config::default(),
+    )
+    .expect("process schemas");
+
+    assert!(!generated.contains("AppVersioned"));
+    assert!(!generated.contains("/versioned_generated.rs"));
+}
+
+#[test]
+fn rejects_missing_versioned_type() {
+    let config = vbare_compiler::Config::default().with_versioned_type("Todo2");
+    let err = process(&[("v1", V1), ("v2", V2)], &config).unwrap_err();
+    assert_eq!(err, "versioned type Todo2 is missing from v1");
+}
+
+#[test]
+fn rejects_version_gap() {
+    let config = vbare_compiler::Config::default().with_versioned_type("App");
+    let err = process(&[("v1", V1), ("v3", V2)], &config).unwrap_err();
+    assert_eq!(err, "schema versions must be contiguous from v1, found v3");
+}
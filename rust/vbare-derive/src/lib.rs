@@ -0,0 +1,689 @@
+//! Derive macros for the `vbare` runtime traits.
+//!
+//! These are re-exported from `vbare` when its `derive` feature is enabled, so depend on `vbare`
+//! rather than on this crate directly.
+//!
+//! The derives apply to an enum with one single-field tuple variant per schema version, named
+//! `V1` through `VN` in order. The last variant is treated as the latest version:
+//!
+//! ```ignore
+//! #[derive(Clone, vbare::OwnedVersionedData)]
+//! #[vbare(
+//!     upgrade = [Self::v1_to_v2, Self::v2_to_v3],
+//!     downgrade = [Self::v3_to_v2, Self::v2_to_v1],
+//! )]
+//! pub enum AppVersioned {
+//!     V1(v1::App),
+//!     V2(v2::App),
+//!     V3(v3::App),
+//! }
+//! ```
+//!
+//! `upgrade` becomes `deserialize_converters` and `downgrade` becomes `serialize_converters`.
+//! Both are optional, but when present they must contain one converter per step between supported
+//! versions.
+//!
+//! Converters take and return the whole enum. Alternatively, `upgrade_steps` and `downgrade_steps`
+//! take one function per step from the payload type of one version to the next, such as
+//! `fn(v1::App) -> anyhow::Result<v2::App>`. The derive wraps each step in a converter for its
+//! variant, so a missing, extra or misordered step fails to compile:
+//!
+//! ```ignore
+//! #[derive(Clone, vbare::OwnedVersionedData)]
+//! #[vbare(upgrade_steps = [v1_to_v2], downgrade_steps = [v2_to_v1])]
+//! pub enum AppVersioned {
+//!     V1(v1::App),
+//!     V2(v2::App),
+//! }
+//!
+//! fn v1_to_v2(app: v1::App) -> anyhow::Result<v2::App> { /* ... */ }
+//! fn v2_to_v1(app: v2::App) -> anyhow::Result<v1::App> { /* ... */ }
+//! ```
+//!
+//! `LATEST_VERSION` is set to the number of variants. Old versions can be rejected with
+//! `#[vbare(min_supported_version = 2)]`, which sets `MIN_SUPPORTED_VERSION`. Converters and steps
+//! to and from versions below the minimum may then be left out.
+//!
+//! Setting `context = Ctx` also implements `ContextOwnedVersionedData<Ctx>` (or
+//! `ContextVersionedData<'a, Ctx>`). Its converters are given with `context_upgrade`,
+//! `context_downgrade`, `context_upgrade_steps` and `context_downgrade_steps`, which follow the
+//! rules above but take a `&mut Ctx` argument after the data:
+//!
+//! ```ignore
+//! #[derive(Clone, vbare::OwnedVersionedData)]
+//! #[vbare(context = Migration, context_upgrade_steps = [Migration::v1_to_v2])]
+//! pub enum AppVersioned {
+//!     V1(v1::App),
+//!     V2(v2::App),
+//! }
+//!
+//! impl Migration {
+//!     fn v1_to_v2(app: v1::App, ctx: &mut Migration) -> anyhow::Result<v2::App> { /* ... */ }
+//! }
+//! ```
+//!
+//! The converters of `OwnedVersionedData` stay empty unless they are given as well. Adding
+//! `default_context` derives them from the context converters instead, running each with
+//! `Ctx::default()`, so `deserialize` and `serialize` keep working for callers without a context.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, quote_spanned};
+use syn::{
+    parse_macro_input, punctuated::Punctuated, spanned::Spanned, Data, DeriveInput, Error, Expr,
+    ExprArray, Fields, GenericParam, Ident, Lifetime, LifetimeParam, LitInt, Token, Type,
+};
+
+/// Derives `vbare::OwnedVersionedData` for an enum of `V1(..)` through `VN(..)` variants.
+#[proc_macro_derive(OwnedVersionedData, attributes(vbare))]
+pub fn derive_owned_versioned_data(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input, Kind::Owned)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Derives `vbare::VersionedData<'a>` for an enum of `V1(..)` through `VN(..)` variants.
+///
+/// If the enum declares a lifetime parameter, the first one is used as the payload lifetime.
+#[proc_macro_derive(VersionedData, attributes(vbare))]
+pub fn derive_versioned_data(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input, Kind::Borrowed)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Owned,
+    Borrowed,
+}
+
+struct Version {
+    variant: Ident,
+    ty: Type,
+    version: u16,
+}
+
+#[derive(Default)]
+struct Attrs {
+    upgrade: Option<Punctuated<Expr, Token![,]>>,
+    downgrade: Option<Punctuated<Expr, Token![,]>>,
+    upgrade_steps: Option<Punctuated<Expr, Token![,]>>,
+    downgrade_steps: Option<Punctuated<Expr, Token![,]>>,
+    min_supported_version: Option<LitInt>,
+    context: Option<Type>,
+    default_context: Option<Ident>,
+    context_upgrade: Option<Punctuated<Expr, Token![,]>>,
+    context_downgrade: Option<Punctuated<Expr, Token![,]>>,
+    context_upgrade_steps: Option<Punctuated<Expr, Token![,]>>,
+    context_downgrade_steps: Option<Punctuated<Expr, Token![,]>>,
+}
+
+fn expand(input: &DeriveInput, kind: Kind) -> syn::Result<TokenStream2> {
+    let versions = parse_versions(input)?;
+    let attrs = parse_attrs(input)?;
+
+    let min_supported_version = match &attrs.min_supported_version {
+        Some(lit) => {
+            let version = lit.base10_parse::<u16>()?;
+            if version == 0 || usize::from(version) > versions.len() {
+                return Err(Error::new(
+                    lit.span(),
+                    format!(
+                        "min_supported_version must be between 1 and {}",
+                        versions.len()
+                    ),
+                ));
+            }
+            version
+        }
+        None => 1,
+    };
+
+    let upgrade = converters(
+        &versions,
+        min_supported_version,
+        ("upgrade", attrs.upgrade),
+        ("upgrade_steps", attrs.upgrade_steps),
+        versions.windows(2).map(|w| (&w[0], &w[1])).collect(),
+        false,
+    )?;
+    let downgrade = converters(
+        &versions,
+        min_supported_version,
+        ("downgrade", attrs.downgrade),
+        ("downgrade_steps", attrs.downgrade_steps),
+        versions.windows(2).rev().map(|w| (&w[1], &w[0])).collect(),
+        false,
+    )?;
+    let context_upgrade = converters(
+        &versions,
+        min_supported_version,
+        ("context_upgrade", attrs.context_upgrade),
+        ("context_upgrade_steps", attrs.context_upgrade_steps),
+        versions.windows(2).map(|w| (&w[0], &w[1])).collect(),
+        true,
+    )?;
+    let context_downgrade = converters(
+        &versions,
+        min_supported_version,
+        ("context_downgrade", attrs.context_downgrade),
+        ("context_downgrade_steps", attrs.context_downgrade_steps),
+        versions.windows(2).rev().map(|w| (&w[1], &w[0])).collect(),
+        true,
+    )?;
+    if attrs.context.is_none() && (context_upgrade.is_some() || context_downgrade.is_some()) {
+        return Err(Error::new(
+            input.ident.span(),
+            "context converters require `context = ...`",
+        ));
+    }
+    if let Some(default_context) = &attrs.default_context {
+        if attrs.context.is_none() {
+            return Err(Error::new(
+                default_context.span(),
+                "`default_context` requires `context = ...`",
+            ));
+        }
+        if (upgrade.is_some() && context_upgrade.is_some())
+            || (downgrade.is_some() && context_downgrade.is_some())
+        {
+            return Err(Error::new(
+                default_context.span(),
+                "`default_context` cannot be used with converters or steps for the same direction",
+            ));
+        }
+    }
+
+    let min_supported_version = attrs
+        .min_supported_version
+        .is_some()
+        .then(|| quote! { const MIN_SUPPORTED_VERSION: u16 = #min_supported_version; });
+
+    let ident = &input.ident;
+    let latest = versions.last().expect("at least one version");
+    let latest_version = latest.version;
+    let latest_variant = &latest.variant;
+    let latest_ty = &latest.ty;
+
+    let deserialize_arms = versions.iter().map(|v| {
+        let (variant, version) = (&v.variant, v.version);
+        quote! {
+            #version => ::core::result::Result::Ok(
+                Self::#variant(::vbare::__private::serde_bare::from_slice(payload)?)
+            ),
+        }
+    });
+    let serialize_arms: Vec<_> = versions
+        .iter()
+        .map(|Version { variant, .. }| {
+            quote! {
+                Self::#variant(data) => ::vbare::__private::serde_bare::to_vec(&data)
+                    .map_err(::core::convert::Into::into),
+            }
+        })
+        .collect();
+
+    let (trait_path, (context_trait, context_args), impl_generics, payload_ty, converters_lifetime) =
+        match kind {
+            Kind::Owned => {
+                let (impl_generics, _, _) = input.generics.split_for_impl();
+                (
+                    quote! { ::vbare::OwnedVersionedData },
+                    (quote! { ::vbare::ContextOwnedVersionedData }, quote! {}),
+                    quote! { #impl_generics },
+                    quote! { &[u8] },
+                    quote! { 'static },
+                )
+            }
+            Kind::Borrowed => {
+                let mut generics = input.generics.clone();
+                let lifetime = match generics.lifetimes().next() {
+                    Some(param) => param.lifetime.clone(),
+                    None => {
+                        let lifetime = Lifetime::new("'__vbare", Span::call_site());
+                        generics.params.insert(
+                            0,
+                            GenericParam::Lifetime(LifetimeParam::new(lifetime.clone())),
+                        );
+                        lifetime
+                    }
+                };
+                let (impl_generics, _, _) = generics.split_for_impl();
+                (
+                    quote! { ::vbare::VersionedData<#lifetime> },
+                    (
+                        quote! { ::vbare::ContextVersionedData },
+                        quote! { #lifetime, },
+                    ),
+                    quote! { #impl_generics },
+                    quote! { &#lifetime [u8] },
+                    quote! { #lifetime },
+                )
+            }
+        };
+    let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    // Plain converters that run the context converter at the same index with a default context
+    let from_context = |getter: TokenStream2, len: usize| -> Vec<TokenStream2> {
+        let ctx = attrs.context.as_ref().expect("checked above");
+        (0..len)
+            .map(|i| {
+                quote! {
+                    |data| (<Self as #context_trait<#context_args #ctx>>::#getter()[#i])(
+                        data,
+                        &mut <#ctx as ::core::default::Default>::default(),
+                    )
+                }
+            })
+            .collect()
+    };
+    let (upgrade, downgrade) = match attrs.default_context {
+        Some(_) => (
+            upgrade.or_else(|| {
+                context_upgrade.as_ref().map(|converters| {
+                    from_context(quote! { deserialize_context_converters }, converters.len())
+                })
+            }),
+            downgrade.or_else(|| {
+                context_downgrade.as_ref().map(|converters| {
+                    from_context(quote! { serialize_context_converters }, converters.len())
+                })
+            }),
+        ),
+        None => (upgrade, downgrade),
+    };
+
+    let deserialize_converters = upgrade.map(|converters| {
+        quote! {
+            fn deserialize_converters() -> &#converters_lifetime [::vbare::Converter<Self>] {
+                &[#(#converters),*]
+            }
+        }
+    });
+    let serialize_converters = downgrade.map(|converters| {
+        quote! {
+            fn serialize_converters() -> &#converters_lifetime [::vbare::Converter<Self>] {
+                &[#(#converters),*]
+            }
+        }
+    });
+
+    let context_impl = attrs.context.map(|ctx| {
+        let deserialize_context_converters = context_upgrade.map(|converters| {
+            quote! {
+                fn deserialize_context_converters(
+                ) -> &#converters_lifetime [::vbare::ContextConverter<Self, #ctx>] {
+                    &[#(#converters),*]
+                }
+            }
+        });
+        let serialize_context_converters = context_downgrade.map(|converters| {
+            quote! {
+                fn serialize_context_converters(
+                ) -> &#converters_lifetime [::vbare::ContextConverter<Self, #ctx>] {
+                    &[#(#converters),*]
+                }
+            }
+        });
+        quote! {
+            impl #impl_generics #context_trait<#context_args #ctx> for #ident #ty_generics #where_clause {
+                #deserialize_context_converters
+
+                #serialize_context_converters
+            }
+        }
+    });
+
+    Ok(quote! {
+        #context_impl
+
+        impl #impl_generics #trait_path for #ident #ty_generics #where_clause {
+            type Latest = #latest_ty;
+
+            const LATEST_VERSION: u16 = #latest_version;
+            #min_supported_version
+
+            fn wrap_latest(latest: Self::Latest) -> Self {
+                Self::#latest_variant(latest)
+            }
+
+            fn unwrap_latest(self) -> ::vbare::__private::anyhow::Result<Self::Latest> {
+                #[allow(irrefutable_let_patterns)]
+                if let Self::#latest_variant(data) = self {
+                    ::core::result::Result::Ok(data)
+                } else {
+                    ::vbare::__private::anyhow::bail!("version not latest");
+                }
+            }
+
+            fn deserialize_version(
+                payload: #payload_ty,
+                version: u16,
+            ) -> ::vbare::__private::anyhow::Result<Self> {
+                match version {
+                    #(#deserialize_arms)*
+                    _ => ::vbare::__private::anyhow::bail!("invalid version: {version}"),
+                }
+            }
+
+            fn serialize_version(
+                self,
+                _version: u16,
+            ) -> ::vbare::__private::anyhow::Result<::vbare::__private::Vec<u8>> {
+                match self {
+                    #(#serialize_arms)*
+                }
+            }
+
+            fn serialize_version_ref(
+                &self,
+                _version: u16,
+            ) -> ::core::option::Option<
+                ::vbare::__private::anyhow::Result<::vbare::__private::Vec<u8>>,
+            > {
+                ::core::option::Option::Some(match self {
+                    #(#serialize_arms)*
+                })
+            }
+
+            #deserialize_converters
+
+            #serialize_converters
+        }
+    })
+}
+
+/// Returns the `Self -> Self` converters for one direction, given either as converters or as typed
+/// steps between the `(from, to)` version pairs, ordered as they are listed.
+///
+/// Converters are matched up from the latest version, so the ones that touch versions below
+/// `min_supported_version` may be left out. Steps also take the context when `context` is set.
+fn converters(
+    versions: &[Version],
+    min_supported_version: u16,
+    (converters_name, converters): (&str, Option<Punctuated<Expr, Token![,]>>),
+    (steps_name, steps): (&str, Option<Punctuated<Expr, Token![,]>>),
+    pairs: Vec<(&Version, &Version)>,
+    context: bool,
+) -> syn::Result<Option<Vec<TokenStream2>>> {
+    let (name, exprs) = match (converters, steps) {
+        (Some(_), Some(steps)) => {
+            return Err(Error::new(
+                steps.span(),
+                format!("`{converters_name}` and `{steps_name}` cannot be used together"),
+            ))
+        }
+        (Some(converters), None) => (converters_name, converters),
+        (None, Some(steps)) => (steps_name, steps),
+        (None, None) => return Ok(None),
+    };
+
+    let required = pairs
+        .iter()
+        .filter(|(from, to)| from.version.min(to.version) >= min_supported_version)
+        .count();
+    if exprs.len() < required || exprs.len() > pairs.len() {
+        let kind = if name == converters_name {
+            "converter(s)"
+        } else {
+            "step(s)"
+        };
+        let expected = if required == pairs.len() {
+            required.to_string()
+        } else {
+            format!("{required} to {}", pairs.len())
+        };
+        return Err(Error::new(
+            exprs.span(),
+            format!(
+                "expected {expected} {name} {kind} for {} versions, found {}",
+                versions.len(),
+                exprs.len()
+            ),
+        ));
+    }
+
+    // Drop the pairs that were left out, which are the ones furthest from the latest version
+    let mut pairs = pairs;
+    let upgrade = pairs
+        .first()
+        .is_some_and(|(from, to)| from.version < to.version);
+    if upgrade {
+        pairs.drain(..pairs.len() - exprs.len());
+    } else {
+        pairs.truncate(exprs.len());
+    }
+
+    if name == converters_name {
+        return Ok(Some(exprs.iter().map(|expr| quote! { #expr }).collect()));
+    }
+
+    // Each step only sees the payload of its own version, so a step for the wrong version or one
+    // returning the wrong version fails to type check.
+    let converters = exprs
+        .iter()
+        .zip(pairs)
+        .map(|(step, (from, to))| {
+            let (from, to) = (&from.variant, &to.variant);
+            if context {
+                quote_spanned! {step.span()=>
+                    |data, ctx| match data {
+                        Self::#from(data) => {
+                            ::core::result::Result::Ok(Self::#to(#step(data, ctx)?))
+                        }
+                        other => ::core::result::Result::Ok(other),
+                    }
+                }
+            } else {
+                quote_spanned! {step.span()=>
+                    |data| match data {
+                        Self::#from(data) => ::core::result::Result::Ok(Self::#to(#step(data)?)),
+                        other => ::core::result::Result::Ok(other),
+                    }
+                }
+            }
+        })
+        .collect();
+    Ok(Some(converters))
+}
+
+fn parse_versions(input: &DeriveInput) -> syn::Result<Vec<Version>> {
+    let Data::Enum(data) = &input.data else {
+        return Err(Error::new(
+            input.ident.span(),
+            "vbare derives only support enums with one variant per version",
+        ));
+    };
+
+    if data.variants.is_empty() {
+        return Err(Error::new(
+            input.ident.span(),
+            "versioned enum must have at least one variant",
+        ));
+    }
+
+    let mut versions = Vec::with_capacity(data.variants.len());
+    for (i, variant) in data.variants.iter().enumerate() {
+        let expected = i + 1;
+        let version = variant
+            .ident
+            .to_string()
+            .strip_prefix('V')
+            .and_then(|n| n.parse::<u16>().ok());
+        if version.map(usize::from) != Some(expected) {
+            return Err(Error::new(
+                variant.ident.span(),
+                format!("expected variant `V{expected}`, variants must be named V1..VN in order"),
+            ));
+        }
+
+        let ty = match &variant.fields {
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => fields.unnamed[0].ty.clone(),
+            _ => {
+                return Err(Error::new(
+                    variant.span(),
+                    "versioned variants must have exactly one unnamed field",
+                ))
+            }
+        };
+
+        versions.push(Version {
+            variant: variant.ident.clone(),
+            ty,
+            version: expected as u16,
+        });
+    }
+
+    Ok(versions)
+}
+
+fn parse_attrs(input: &DeriveInput) -> syn::Result<Attrs> {
+    let mut attrs = Attrs::default();
+
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("vbare")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("min_supported_version") {
+                if attrs.min_supported_version.is_some() {
+                    return Err(meta.error("duplicate vbare attribute"));
+                }
+
+                attrs.min_supported_version = Some(meta.value()?.parse()?);
+                return Ok(());
+            }
+
+            if meta.path.is_ident("default_context") {
+                if attrs.default_context.is_some() {
+                    return Err(meta.error("duplicate vbare attribute"));
+                }
+
+                attrs.default_context = meta.path.get_ident().cloned();
+                return Ok(());
+            }
+
+            if meta.path.is_ident("context") {
+                if attrs.context.is_some() {
+                    return Err(meta.error("duplicate vbare attribute"));
+                }
+
+                attrs.context = Some(meta.value()?.parse()?);
+                return Ok(());
+            }
+
+            let slot = if meta.path.is_ident("upgrade") {
+                &mut attrs.upgrade
+            } else if meta.path.is_ident("downgrade") {
+                &mut attrs.downgrade
+            } else if meta.path.is_ident("upgrade_steps") {
+                &mut attrs.upgrade_steps
+            } else if meta.path.is_ident("downgrade_steps") {
+                &mut attrs.downgrade_steps
+            } else if meta.path.is_ident("context_upgrade") {
+                &mut attrs.context_upgrade
+            } else if meta.path.is_ident("context_downgrade") {
+                &mut attrs.context_downgrade
+            } else if meta.path.is_ident("context_upgrade_steps") {
+                &mut attrs.context_upgrade_steps
+            } else if meta.path.is_ident("context_downgrade_steps") {
+                &mut attrs.context_downgrade_steps
+            } else {
+                return Err(meta.error(
+                    "unknown vbare attribute, expected `upgrade`, `downgrade`, `upgrade_steps`, \
+                     `downgrade_steps`, `min_supported_version`, `context`, `default_context`, \
+                     `context_upgrade`, `context_downgrade`, `context_upgrade_steps` or \
+                     `context_downgrade_steps`",
+                ));
+            };
+
+            if slot.is_some() {
+                return Err(meta.error("duplicate vbare attribute"));
+            }
+
+            let converters: ExprArray = meta.value()?.parse()?;
+            *slot = Some(converters.elems);
+            Ok(())
+        })?;
+    }
+
+    Ok(attrs)
+}
+
+/// Mistakes the derive must reject at compile time. The steps below compile:
+///
+/// ```
+/// #[derive(Clone, vbare::OwnedVersionedData)]
+/// #[vbare(upgrade_steps = [v1_to_v2, v2_to_v3])]
+/// enum Counter {
+///     V1(u8),
+///     V2(u16),
+///     V3(u32),
+/// }
+///
+/// fn v1_to_v2(n: u8) -> anyhow::Result<u16> { Ok(n.into()) }
+/// fn v2_to_v3(n: u16) -> anyhow::Result<u32> { Ok(n.into()) }
+/// ```
+///
+/// A missing step:
+///
+/// ```compile_fail
+/// #[derive(Clone, vbare::OwnedVersionedData)]
+/// #[vbare(upgrade_steps = [v1_to_v2])]
+/// enum Counter {
+///     V1(u8),
+///     V2(u16),
+///     V3(u32),
+/// }
+///
+/// fn v1_to_v2(n: u8) -> anyhow::Result<u16> { Ok(n.into()) }
+/// ```
+///
+/// Steps in the wrong order:
+///
+/// ```compile_fail,E0308
+/// #[derive(Clone, vbare::OwnedVersionedData)]
+/// #[vbare(upgrade_steps = [v2_to_v3, v1_to_v2])]
+/// enum Counter {
+///     V1(u8),
+///     V2(u16),
+///     V3(u32),
+/// }
+///
+/// fn v1_to_v2(n: u8) -> anyhow::Result<u16> { Ok(n.into()) }
+/// fn v2_to_v3(n: u16) -> anyhow::Result<u32> { Ok(n.into()) }
+/// ```
+///
+/// Converters and steps for the same direction:
+///
+/// ```compile_fail
+/// #[derive(Clone, vbare::OwnedVersionedData)]
+/// #[vbare(upgrade = [Counter::upgrade], upgrade_steps = [v1_to_v2])]
+/// enum Counter {
+///     V1(u8),
+///     V2(u16),
+/// }
+///
+/// impl Counter {
+///     fn upgrade(self) -> anyhow::Result<Self> {
+///         match self {
+///             Counter::V1(n) => Ok(Counter::V2(n.into())),
+///             other => Ok(other),
+///         }
+///     }
+/// }
+///
+/// fn v1_to_v2(n: u8) -> anyhow::Result<u16> { Ok(n.into()) }
+/// ```
+///
+/// Variants not named `V1` through `VN`:
+///
+/// ```compile_fail
+/// #[derive(Clone, vbare::OwnedVersionedData)]
+/// enum Counter {
+///     V1(u8),
+///     V3(u32),
+/// }
+/// ```
+#[cfg(doctest)]
+struct CompileFail;
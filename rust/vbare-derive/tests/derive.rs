@@ -0,0 +1,477 @@
+use std::borrow::Cow;
+
+use anyhow::*;
+use serde::{Deserialize, Serialize};
+use vbare::{ContextOwnedVersionedData, ContextVersionedData, OwnedVersionedData, VersionedData};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TestDataV1 {
+    id: u32,
+    name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TestDataV2 {
+    id: u32,
+    name: String,
+    description: String,
+}
+
+#[derive(Clone, OwnedVersionedData)]
+#[vbare(upgrade = [Self::v1_to_v2], downgrade = [Self::v2_to_v1])]
+enum TestData {
+    V1(TestDataV1),
+    V2(TestDataV2),
+}
+
+impl TestData {
+    fn v1_to_v2(self) -> Result<Self> {
+        match self {
+            TestData::V1(v1) => Ok(TestData::V2(TestDataV2 {
+                id: v1.id,
+                name: v1.name,
+                description: "default".to_string(),
+            })),
+            other => Ok(other),
+        }
+    }
+
+    fn v2_to_v1(self) -> Result<Self> {
+        match self {
+            TestData::V2(v2) => Ok(TestData::V1(TestDataV1 {
+                id: v2.id,
+                name: v2.name,
+            })),
+            other => Ok(other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, OwnedVersionedData)]
+#[vbare(upgrade_steps = [v1_to_v2], downgrade_steps = [v2_to_v1])]
+enum TestDataSteps {
+    V1(TestDataV1),
+    V2(TestDataV2),
+}
+
+fn v1_to_v2(v1: TestDataV1) -> Result<TestDataV2> {
+    Ok(TestDataV2 {
+        id: v1.id,
+        name: v1.name,
+        description: "default".to_string(),
+    })
+}
+
+fn v2_to_v1(v2: TestDataV2) -> Result<TestDataV1> {
+    if v2.name.is_empty() {
+        bail!("name must not be empty");
+    }
+    Ok(TestDataV1 {
+        id: v2.id,
+        name: v2.name,
+    })
+}
+
+#[derive(Clone, OwnedVersionedData)]
+enum TestDataNoConverters {
+    V1(TestDataV1),
+}
+
+#[derive(Clone, OwnedVersionedData)]
+#[vbare(min_supported_version = 2, upgrade = [Self::v1_to_v2])]
+enum TestDataSunset {
+    V1(TestDataV1),
+    V2(TestDataV2),
+}
+
+impl TestDataSunset {
+    fn v1_to_v2(self) -> Result<Self> {
+        match self {
+            TestDataSunset::V1(v1) => Ok(TestDataSunset::V2(TestDataV2 {
+                id: v1.id,
+                name: v1.name,
+                description: String::new(),
+            })),
+            other => Ok(other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TestDataV3 {
+    id: u64,
+    name: String,
+}
+
+// Converters for the unsupported version 1 are left out
+#[derive(Clone, OwnedVersionedData)]
+#[vbare(min_supported_version = 3, upgrade = [Self::v2_to_v3])]
+enum TestDataRetired {
+    V1(TestDataV1),
+    V2(TestDataV2),
+    V3(TestDataV3),
+}
+
+impl TestDataRetired {
+    fn v2_to_v3(self) -> Result<Self> {
+        match self {
+            TestDataRetired::V2(v2) => Ok(TestDataRetired::V3(TestDataV3 {
+                id: v2.id.into(),
+                name: v2.name,
+            })),
+            other => Ok(other),
+        }
+    }
+}
+
+#[derive(Clone, OwnedVersionedData)]
+#[vbare(
+    min_supported_version = 2,
+    upgrade_steps = [v2_to_v3],
+    downgrade_steps = [v3_to_v2],
+)]
+enum TestDataRetiredSteps {
+    V1(TestDataV1),
+    V2(TestDataV2),
+    V3(TestDataV3),
+}
+
+fn v2_to_v3(v2: TestDataV2) -> Result<TestDataV3> {
+    Ok(TestDataV3 {
+        id: v2.id.into(),
+        name: v2.name,
+    })
+}
+
+fn v3_to_v2(v3: TestDataV3) -> Result<TestDataV2> {
+    Ok(TestDataV2 {
+        id: v3.id.try_into()?,
+        name: v3.name,
+        description: String::new(),
+    })
+}
+
+/// Context for `TestDataContext`.
+#[derive(Default)]
+struct Defaults {
+    description: String,
+    downgraded: Vec<u32>,
+}
+
+#[derive(Debug, Clone, OwnedVersionedData)]
+#[vbare(
+    context = Defaults,
+    context_upgrade_steps = [describe],
+    context_downgrade_steps = [forget],
+)]
+enum TestDataContext {
+    V1(TestDataV1),
+    V2(TestDataV2),
+}
+
+#[derive(Debug, Clone, OwnedVersionedData)]
+#[vbare(
+    context = Defaults,
+    default_context,
+    context_upgrade_steps = [describe],
+    context_downgrade_steps = [forget],
+)]
+enum TestDataDefaultContext {
+    V1(TestDataV1),
+    V2(TestDataV2),
+}
+
+fn describe(v1: TestDataV1, defaults: &mut Defaults) -> Result<TestDataV2> {
+    Ok(TestDataV2 {
+        id: v1.id,
+        name: v1.name,
+        description: defaults.description.clone(),
+    })
+}
+
+fn forget(v2: TestDataV2, defaults: &mut Defaults) -> Result<TestDataV1> {
+    defaults.downgraded.push(v2.id);
+    Ok(TestDataV1 {
+        id: v2.id,
+        name: v2.name,
+    })
+}
+
+#[derive(VersionedData)]
+#[vbare(context = str, context_upgrade = [Self::prefix])]
+enum PrefixedName<'a> {
+    V1(Cow<'a, str>),
+    V2(Cow<'a, str>),
+}
+
+impl PrefixedName<'_> {
+    fn prefix(self, prefix: &mut str) -> Result<Self> {
+        match self {
+            PrefixedName::V1(name) => Ok(PrefixedName::V2(Cow::Owned(format!("{prefix}{name}")))),
+            other => Ok(other),
+        }
+    }
+}
+
+#[derive(VersionedData)]
+enum WithLifetime<'a> {
+    V1(Cow<'a, str>),
+}
+
+#[derive(VersionedData)]
+enum OwnedAsBorrowed {
+    V1(TestDataV1),
+}
+
+#[test]
+fn test_derive_upgrade() {
+    let payload = serde_bare::to_vec(&TestDataV1 {
+        id: 1,
+        name: "test".to_string(),
+    })
+    .unwrap();
+
+    let deserialized = TestData::deserialize(&payload, 1).unwrap();
+    assert_eq!(
+        deserialized,
+        TestDataV2 {
+            id: 1,
+            name: "test".to_string(),
+            description: "default".to_string(),
+        }
+    );
+}
+
+#[test]
+fn test_derive_downgrade() {
+    let data = TestDataV2 {
+        id: 2,
+        name: "test".to_string(),
+        description: "will be stripped".to_string(),
+    };
+
+    let payload = TestData::wrap_latest(data.clone()).serialize(1).unwrap();
+    let deserialized: TestDataV1 = serde_bare::from_slice(&payload).unwrap();
+    assert_eq!(deserialized.id, 2);
+    assert_eq!(deserialized.name, "test");
+
+    let payload = TestData::wrap_latest(data.clone())
+        .serialize_with_embedded_version(2)
+        .unwrap();
+    assert_eq!(
+        TestData::deserialize_with_embedded_version(&payload).unwrap(),
+        data
+    );
+}
+
+#[test]
+fn test_derive_serialize_ref() {
+    let data = TestData::wrap_latest(TestDataV2 {
+        id: 3,
+        name: "test".to_string(),
+        description: "shared".to_string(),
+    });
+
+    assert!(data.serialize_version_ref(2).is_some());
+    for version in 1..=2 {
+        assert_eq!(
+            data.serialize_ref(version).unwrap(),
+            data.clone().serialize(version).unwrap()
+        );
+    }
+
+    let borrowed = WithLifetime::wrap_latest(Cow::Borrowed("borrowed"));
+    assert_eq!(
+        borrowed.serialize_version_ref(1).unwrap().unwrap(),
+        serde_bare::to_vec("borrowed").unwrap()
+    );
+}
+
+#[test]
+fn test_derive_typed_steps() {
+    let v1 = TestDataV1 {
+        id: 7,
+        name: "steps".to_string(),
+    };
+    let payload = serde_bare::to_vec(&v1).unwrap();
+    let latest = TestDataSteps::deserialize(&payload, 1).unwrap();
+    assert_eq!(
+        latest,
+        TestDataV2 {
+            id: 7,
+            name: "steps".to_string(),
+            description: "default".to_string(),
+        }
+    );
+
+    let payload = TestDataSteps::wrap_latest(latest).serialize(1).unwrap();
+    assert_eq!(serde_bare::from_slice::<TestDataV1>(&payload).unwrap(), v1);
+
+    // Step errors are reported for the step that failed
+    let err = TestDataSteps::wrap_latest(TestDataV2 {
+        id: 8,
+        name: String::new(),
+        description: String::new(),
+    })
+    .serialize(1)
+    .unwrap_err();
+    assert!(matches!(err, vbare::Error::Convert { from: 2, to: 1, .. }));
+}
+
+#[test]
+fn test_derive_unwrap_latest() {
+    let data = TestData::V1(TestDataV1 {
+        id: 3,
+        name: "test".to_string(),
+    });
+    assert!(data.unwrap_latest().is_err());
+}
+
+#[test]
+fn test_derive_unsupported_version() {
+    assert!(TestData::deserialize(&[], 3).is_err());
+    assert!(TestDataNoConverters::deserialize(&[], 2).is_err());
+}
+
+#[test]
+fn test_derive_supported_versions() {
+    assert_eq!(TestData::LATEST_VERSION, 2);
+    assert_eq!(TestData::MIN_SUPPORTED_VERSION, 1);
+    assert_eq!(TestDataNoConverters::LATEST_VERSION, 1);
+    assert_eq!(
+        TestDataSunset::supported_versions(),
+        vbare::VersionRange::new(2, 2).unwrap()
+    );
+    assert_eq!(<WithLifetime as VersionedData>::LATEST_VERSION, 1);
+
+    let payload = serde_bare::to_vec(&TestDataV1 {
+        id: 6,
+        name: "old".to_string(),
+    })
+    .unwrap();
+    assert!(matches!(
+        TestDataSunset::deserialize(&payload, 1),
+        Err(vbare::Error::VersionTooOld { got: 1, min: 2 })
+    ));
+}
+
+#[test]
+fn test_derive_retired_versions() {
+    let v2 = TestDataV2 {
+        id: 9,
+        name: "retired".to_string(),
+        description: String::new(),
+    };
+    let payload = serde_bare::to_vec(&v2).unwrap();
+    assert_eq!(TestDataRetired::deserialize_converters().len(), 1);
+    assert!(matches!(
+        TestDataRetired::deserialize(&payload, 2),
+        Err(vbare::Error::VersionTooOld { got: 2, min: 3 })
+    ));
+
+    let latest = TestDataRetiredSteps::deserialize(&payload, 2).unwrap();
+    assert_eq!(
+        latest,
+        TestDataV3 {
+            id: 9,
+            name: "retired".to_string(),
+        }
+    );
+    let payload = TestDataRetiredSteps::wrap_latest(latest)
+        .serialize(2)
+        .unwrap();
+    assert_eq!(serde_bare::from_slice::<TestDataV2>(&payload).unwrap(), v2);
+    assert!(matches!(
+        TestDataRetiredSteps::wrap_latest(TestDataV3 {
+            id: 9,
+            name: "retired".to_string(),
+        })
+        .serialize(1),
+        Err(vbare::Error::VersionTooOld { got: 1, min: 2 })
+    ));
+}
+
+#[test]
+fn test_derive_no_converters() {
+    let data = TestDataV1 {
+        id: 4,
+        name: "test".to_string(),
+    };
+
+    let payload = TestDataNoConverters::wrap_latest(data.clone())
+        .serialize(1)
+        .unwrap();
+    assert_eq!(
+        TestDataNoConverters::deserialize(&payload, 1).unwrap(),
+        data
+    );
+}
+
+#[test]
+fn test_derive_borrowed() {
+    let payload = WithLifetime::wrap_latest(Cow::Borrowed("borrowed"))
+        .serialize(1)
+        .unwrap();
+    assert_eq!(WithLifetime::deserialize(&payload, 1).unwrap(), "borrowed");
+
+    let data = TestDataV1 {
+        id: 5,
+        name: "test".to_string(),
+    };
+    let payload = OwnedAsBorrowed::wrap_latest(data.clone())
+        .serialize_with_embedded_version(1)
+        .unwrap();
+    assert_eq!(
+        OwnedAsBorrowed::deserialize_with_embedded_version(&payload).unwrap(),
+        data
+    );
+}
+
+#[test]
+fn test_derive_context() {
+    let mut defaults = Defaults {
+        description: "from context".to_string(),
+        downgraded: Vec::new(),
+    };
+    let payload = serde_bare::to_vec(&TestDataV1 {
+        id: 3,
+        name: "test".to_string(),
+    })
+    .unwrap();
+
+    let v2 = TestDataContext::deserialize_with_context(&payload, 1, &mut defaults).unwrap();
+    assert_eq!(v2.description, "from context");
+
+    let payload = TestDataContext::wrap_latest(v2)
+        .serialize_with_context(1, &mut defaults)
+        .unwrap();
+    assert_eq!(
+        serde_bare::from_slice::<TestDataV1>(&payload).unwrap().name,
+        "test"
+    );
+    assert_eq!(defaults.downgraded, vec![3]);
+
+    // The context converters are separate from the plain ones
+    assert!(matches!(
+        TestDataContext::deserialize(&payload, 1),
+        Err(vbare::Error::NoMigrationPath { from: 1, to: 2 })
+    ));
+
+    // With `default_context` the plain converters run the context ones with a default context
+    let v2 = TestDataDefaultContext::deserialize(&payload, 1).unwrap();
+    assert_eq!(v2.description, "");
+    let payload = TestDataDefaultContext::wrap_latest(v2)
+        .serialize(1)
+        .unwrap();
+    assert_eq!(
+        serde_bare::from_slice::<TestDataV1>(&payload).unwrap().id,
+        3
+    );
+
+    let mut prefix = String::from("user:");
+    let payload = serde_bare::to_vec("ada").unwrap();
+    assert_eq!(
+        PrefixedName::deserialize_with_context(&payload, 1, prefix.as_mut_str()).unwrap(),
+        "user:ada"
+    );
+}
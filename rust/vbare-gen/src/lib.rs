@@ -95,20 +95,12 @@ use quote::quote;
 mod parser;
 
 /// Configuration for `bare_schema` code generation.
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 pub struct Config {
     /// When true, generated maps use `rivet_util::serde::HashableMap`.
     pub use_hashable_map: bool,
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            use_hashable_map: false,
-        }
-    }
-}
-
 impl Config {
     /// Convenience constructor to emit `HashMap` rather than `HashableMap`.
     pub fn with_hash_map() -> Self {
@@ -123,7 +115,7 @@ impl Config {
     }
 }
 
-fn ident_from_string(s: &String) -> Ident {
+fn ident_from_string(s: &str) -> Ident {
     Ident::new(s, Span::call_site())
 }
 
@@ -142,7 +134,7 @@ pub fn bare_schema(schema_path: &Path, config: Config) -> proc_macro2::TokenStre
     };
 
     for (name, user_type) in &schema_generator.user_type_registry.clone() {
-        schema_generator.gen_user_type(&name, &user_type);
+        schema_generator.gen_user_type(name, user_type);
     }
 
     schema_generator.complete()
@@ -173,8 +165,7 @@ impl SchemaGenerator {
     /// level. Rust does not support anonymous structs/enums/etc., so we must recursively parse any
     /// anonymous definitions and generate top-level definitions. As such, this function may generate
     /// multiple types.
-    fn gen_user_type(&mut self, name: &String, t: &AnyType) {
-        #[allow(unused_assignments)]
+    fn gen_user_type(&mut self, name: &str, t: &AnyType) {
         use AnyType::*;
         let def = match t {
             Primative(p) => {
@@ -227,7 +218,7 @@ impl SchemaGenerator {
         self.global_output.push(def);
     }
 
-    fn dispatch_type(&mut self, name: &String, any_type: &AnyType) -> TokenStream {
+    fn dispatch_type(&mut self, name: &str, any_type: &AnyType) -> TokenStream {
         match any_type {
             AnyType::Primative(p) => gen_primative_type_def(p),
             AnyType::List { inner, length } => self.gen_list(name, inner.as_ref(), length),
@@ -243,7 +234,7 @@ impl SchemaGenerator {
         }
     }
 
-    fn gen_map(&mut self, name: &String, key: &AnyType, value: &AnyType) -> TokenStream {
+    fn gen_map(&mut self, name: &str, key: &AnyType, value: &AnyType) -> TokenStream {
         let key_def = self.dispatch_type(name, key);
         let val_def = self.dispatch_type(name, value);
         if self.config.use_hashable_map {
@@ -257,12 +248,7 @@ impl SchemaGenerator {
         }
     }
 
-    fn gen_list(
-        &mut self,
-        name: &String,
-        inner_type: &AnyType,
-        size: &Option<usize>,
-    ) -> TokenStream {
+    fn gen_list(&mut self, name: &str, inner_type: &AnyType, size: &Option<usize>) -> TokenStream {
         let inner_def = self.dispatch_type(name, inner_type);
         match *size {
             Some(size) if size <= 32 => quote! {
@@ -274,9 +260,9 @@ impl SchemaGenerator {
         }
     }
 
-    fn gen_struct(&mut self, name: &String, fields: &Vec<StructField>) -> TokenStream {
+    fn gen_struct(&mut self, name: &str, fields: &[StructField]) -> TokenStream {
         // clone so we can safely drain this
-        let fields_clone = fields.clone();
+        let fields_clone = fields.to_vec();
         let fields_gen = self.gen_struct_field(name, fields_clone);
         let hash_derive = if self.config.use_hashable_map {
             quote! { , Hash }
@@ -293,12 +279,12 @@ impl SchemaGenerator {
         })
     }
 
-    fn gen_union(&mut self, name: &String, members: &Vec<AnyType>) -> TokenStream {
+    fn gen_union(&mut self, name: &str, members: &[AnyType]) -> TokenStream {
         let mut members_def: Vec<TokenStream> = Vec::with_capacity(members.len());
         for (i, member) in members.iter().enumerate() {
             // If this member is a user type alias for void, we'll not generate an inner type later
             let is_void_type = match member {
-                AnyType::TypeReference(i) if self.user_type_registry.get(i).is_some() => {
+                AnyType::TypeReference(i) if self.user_type_registry.contains_key(i) => {
                     let reference = self.user_type_registry.get(i).unwrap();
                     matches!(reference, AnyType::Primative(PrimativeType::Void))
                 }
@@ -351,7 +337,7 @@ impl SchemaGenerator {
         })
     }
 
-    fn gen_option(&mut self, name: &String, inner: &AnyType) -> TokenStream {
+    fn gen_option(&mut self, name: &str, inner: &AnyType) -> TokenStream {
         let inner_def = self.dispatch_type(name, inner);
         quote! {
            Option<#inner_def>
@@ -360,7 +346,7 @@ impl SchemaGenerator {
 
     fn gen_struct_field(
         &mut self,
-        struct_name: &String,
+        struct_name: &str,
         fields: Vec<StructField>,
     ) -> Vec<TokenStream> {
         let mut fields_gen: Vec<TokenStream> = Vec::with_capacity(fields.len());
@@ -376,7 +362,7 @@ impl SchemaGenerator {
         fields_gen
     }
 
-    fn gen_enum(&mut self, name: &String, members: &Vec<(String, Option<usize>)>) -> TokenStream {
+    fn gen_enum(&mut self, name: &str, members: &[(String, Option<usize>)]) -> TokenStream {
         let member_defs = members.iter().map(|(name, val)| {
             let ident = ident_from_string(&name.to_upper_camel_case());
             if let Some(val) = val {
@@ -411,7 +397,7 @@ impl SchemaGenerator {
     /// schema but not allowed by be defined anonymously in Rust.
     fn gen_anonymous(
         &mut self,
-        name: &String,
+        name: &str,
         inner: impl FnOnce(Ident) -> TokenStream,
     ) -> TokenStream {
         let ident = ident_from_string(name);
@@ -5,7 +5,7 @@ use std::collections::BTreeMap;
 
 #[derive(Parser)]
 #[grammar = "grammar.pest"]
-struct BARE;
+struct Bare;
 
 pub type Length = usize;
 
@@ -54,7 +54,7 @@ pub struct StructField {
 }
 
 pub fn parse_string(schema: &str) -> BTreeMap<String, AnyType> {
-    let schema = BARE::parse(Rule::schema, &schema)
+    let schema = Bare::parse(Rule::schema, schema)
         .unwrap_or_else(|e| panic!("{}", e))
         .next()
         .unwrap(); // this can't fail if parsing didn't fail
@@ -146,7 +146,7 @@ fn parse_enum(pair: Pair<'_, Rule>) -> AnyType {
         assert!(enum_value.as_rule() == Rule::enum_value);
         let mut e = enum_value.into_inner();
         let enum_value_name = e.next().unwrap();
-        let value: Option<usize> = e.next().and_then(|e| Some(e.as_str().parse().unwrap()));
+        let value: Option<usize> = e.next().map(|e| e.as_str().parse().unwrap());
         members.push((enum_value_name.as_str().into(), value));
     }
     AnyType::Enum(members)
@@ -158,7 +158,7 @@ fn parse_list(registry: &BTreeMap<String, AnyType>, pair: Pair<'_, Rule>) -> Any
     let inner = parse_any_type(registry, list_type);
     let length: Option<usize> = list
         .next()
-        .and_then(|e: Pair<'_, Rule>| Some(e.as_str().parse().unwrap()));
+        .map(|e: Pair<'_, Rule>| e.as_str().parse().unwrap());
     AnyType::List {
         inner: Box::new(inner),
         length,
@@ -166,9 +166,9 @@ fn parse_list(registry: &BTreeMap<String, AnyType>, pair: Pair<'_, Rule>) -> Any
 }
 
 fn parse_struct(registry: &BTreeMap<String, AnyType>, pair: Pair<'_, Rule>) -> AnyType {
-    let mut st = pair.into_inner();
+    let st = pair.into_inner();
     let mut fields: Vec<StructField> = Vec::new();
-    while let Some(struct_t) = st.next() {
+    for struct_t in st {
         let mut struct_field = struct_t.into_inner();
         let field_name = struct_field.next().unwrap();
         let field_type = struct_field.next().unwrap();
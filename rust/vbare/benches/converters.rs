@@ -0,0 +1,132 @@
+//! Measures converter chain overhead on small messages, where it dominates.
+//!
+//! The `vec_per_call` cases additionally collect the converters into a `Vec` on every call, which
+//! is what `deserialize_converters`/`serialize_converters` used to do before they returned static
+//! slices.
+
+use std::hint::black_box;
+
+use anyhow::*;
+use criterion::{criterion_group, criterion_main, Criterion};
+use serde::{Deserialize, Serialize};
+use vbare::{Converter, OwnedVersionedData};
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Id(u32);
+
+#[derive(Clone)]
+enum Data {
+    V1(Id),
+    V2(Id),
+    V3(Id),
+    V4(Id),
+}
+
+impl OwnedVersionedData for Data {
+    type Latest = Id;
+
+    const LATEST_VERSION: u16 = 4;
+
+    fn wrap_latest(latest: Id) -> Self {
+        Data::V4(latest)
+    }
+
+    fn unwrap_latest(self) -> Result<Id> {
+        match self {
+            Data::V4(data) => Ok(data),
+            _ => bail!("version not latest"),
+        }
+    }
+
+    fn deserialize_version(payload: &[u8], version: u16) -> Result<Self> {
+        let data = serde_bare::from_slice(payload)?;
+        match version {
+            1 => Ok(Data::V1(data)),
+            2 => Ok(Data::V2(data)),
+            3 => Ok(Data::V3(data)),
+            4 => Ok(Data::V4(data)),
+            _ => bail!("invalid version: {version}"),
+        }
+    }
+
+    fn serialize_version(self, _version: u16) -> Result<Vec<u8>> {
+        match self {
+            Data::V1(data) | Data::V2(data) | Data::V3(data) | Data::V4(data) => {
+                serde_bare::to_vec(&data).map_err(Into::into)
+            }
+        }
+    }
+
+    fn deserialize_converters() -> &'static [Converter<Self>] {
+        &[
+            |data| match data {
+                Data::V1(id) => Ok(Data::V2(id)),
+                other => Ok(other),
+            },
+            |data| match data {
+                Data::V2(id) => Ok(Data::V3(id)),
+                other => Ok(other),
+            },
+            |data| match data {
+                Data::V3(id) => Ok(Data::V4(id)),
+                other => Ok(other),
+            },
+        ]
+    }
+
+    fn serialize_converters() -> &'static [Converter<Self>] {
+        &[
+            |data| match data {
+                Data::V4(id) => Ok(Data::V3(id)),
+                other => Ok(other),
+            },
+            |data| match data {
+                Data::V3(id) => Ok(Data::V2(id)),
+                other => Ok(other),
+            },
+            |data| match data {
+                Data::V2(id) => Ok(Data::V1(id)),
+                other => Ok(other),
+            },
+        ]
+    }
+}
+
+fn deserialize_vec_per_call(payload: &[u8], version: u16) -> vbare::Result<Id> {
+    let converters: Vec<Converter<Data>> = Data::deserialize_converters().to_vec();
+    black_box(&converters);
+    Data::deserialize(payload, version)
+}
+
+fn serialize_vec_per_call(data: Data, version: u16) -> vbare::Result<Vec<u8>> {
+    let converters: Vec<Converter<Data>> = Data::serialize_converters().to_vec();
+    black_box(&converters);
+    data.serialize(version)
+}
+
+fn bench_deserialize(c: &mut Criterion) {
+    let payload = serde_bare::to_vec(&Id(7)).unwrap();
+
+    let mut group = c.benchmark_group("deserialize_v1_to_v4");
+    group.bench_function("static_slice", |b| {
+        b.iter(|| Data::deserialize(black_box(&payload), black_box(1)).unwrap())
+    });
+    group.bench_function("vec_per_call", |b| {
+        b.iter(|| deserialize_vec_per_call(black_box(&payload), black_box(1)).unwrap())
+    });
+    group.finish();
+}
+
+fn bench_serialize(c: &mut Criterion) {
+    let mut group = c.benchmark_group("serialize_v4_to_v1");
+    group.bench_function("static_slice", |b| {
+        b.iter(|| Data::wrap_latest(Id(7)).serialize(black_box(1)).unwrap())
+    });
+    group.bench_function("vec_per_call", |b| {
+        b.iter(|| serialize_vec_per_call(Data::wrap_latest(Id(7)), black_box(1)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_deserialize, bench_serialize);
+criterion_main!(benches);
@@ -0,0 +1,116 @@
+use alloc::{boxed::Box, vec::Vec};
+use core::{future::Future, pin::Pin};
+
+use crate::{
+    check_version,
+    migration::{self, NoShortcut},
+    observe, Error, OwnedVersionedData, Result,
+};
+
+/// Converts data between two adjacent versions using a caller-supplied context, possibly awaiting
+/// on the way. Variants of other versions are passed through unchanged.
+pub type AsyncConverter<T, Ctx> =
+    fn(T, &Ctx) -> Pin<Box<dyn Future<Output = anyhow::Result<T>> + Send + '_>>;
+
+/// `OwnedVersionedData` with converters that can await, e.g. to look up a database row or cache
+/// entry while upgrading. The converters borrow a caller-supplied context, such as a connection
+/// pool; use `()` if they need none.
+///
+/// Async converters are matched up with versions like the converters of `OwnedVersionedData`,
+/// which `deserialize_async` and `serialize_async` do not use.
+pub trait AsyncOwnedVersionedData<Ctx: ?Sized + Sync + 'static>: OwnedVersionedData + Send {
+    /// Converters from each version to the next, ending with the one that produces
+    /// `LATEST_VERSION`. See `VersionedData::deserialize_converters`.
+    fn deserialize_async_converters() -> &'static [AsyncConverter<Self, Ctx>] {
+        &[]
+    }
+
+    /// Converters from each version to the previous, starting with the one that takes
+    /// `LATEST_VERSION`. See `VersionedData::serialize_converters`.
+    fn serialize_async_converters() -> &'static [AsyncConverter<Self, Ctx>] {
+        &[]
+    }
+
+    // See OwnedVersionedData::deserialize.
+    fn deserialize_async(
+        payload: &[u8],
+        version: u16,
+        ctx: &Ctx,
+    ) -> impl Future<Output = Result<Self::Latest>> + Send {
+        let type_name = core::any::type_name::<Self>();
+        observe::deserialize_async(type_name, version, Self::LATEST_VERSION, async move {
+            check_version(version, Self::supported_versions())?;
+
+            let path = migration::upgrade(
+                version,
+                Self::LATEST_VERSION,
+                Self::deserialize_async_converters(),
+                &[] as &[NoShortcut<_>],
+            )?;
+
+            let mut data = Self::deserialize_version(payload, version)
+                .map_err(|source| Error::Decode { version, source })?;
+
+            for (step, convert) in path {
+                data = observe::step_async(type_name, step, convert(data, ctx)).await?;
+            }
+
+            data.unwrap_latest()
+                .map_err(|source| Error::NotLatest { source })
+        })
+    }
+
+    // See OwnedVersionedData::serialize.
+    fn serialize_async(
+        self,
+        version: u16,
+        ctx: &Ctx,
+    ) -> impl Future<Output = Result<Vec<u8>>> + Send {
+        let type_name = core::any::type_name::<Self>();
+        observe::serialize_async(type_name, version, Self::LATEST_VERSION, async move {
+            check_version(version, Self::supported_versions())?;
+
+            let path = migration::downgrade(
+                Self::LATEST_VERSION,
+                Self::LATEST_VERSION,
+                version,
+                Self::serialize_async_converters(),
+                &[] as &[NoShortcut<_>],
+            )?;
+
+            let mut data = self;
+            for (step, convert) in path {
+                data = observe::step_async(type_name, step, convert(data, ctx)).await?;
+            }
+
+            Self::serialize_version(data, version)
+                .map_err(|source| Error::Encode { version, source })
+        })
+    }
+
+    // See OwnedVersionedData::deserialize_with_embedded_version.
+    fn deserialize_with_embedded_version_async(
+        payload: &[u8],
+        ctx: &Ctx,
+    ) -> impl Future<Output = Result<Self::Latest>> + Send {
+        async move {
+            let version = crate::peek_version(payload)?;
+            Self::deserialize_async(&payload[2..], version, ctx).await
+        }
+    }
+
+    // See OwnedVersionedData::serialize_with_embedded_version.
+    fn serialize_with_embedded_version_async(
+        self,
+        version: u16,
+        ctx: &Ctx,
+    ) -> impl Future<Output = Result<Vec<u8>>> + Send {
+        async move {
+            let payload = self.serialize_async(version, ctx).await?;
+            let mut result = Vec::with_capacity(2 + payload.len());
+            result.extend_from_slice(&version.to_le_bytes());
+            result.extend_from_slice(&payload);
+            Ok(result)
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Migrating many embedded-version payloads, e.g. to rewrite a table of stored records at the
+//! latest version.
+
+use alloc::{borrow::Cow, vec::Vec};
+use core::{fmt, marker::PhantomData};
+
+use crate::{Error, OwnedVersionedData, Result};
+
+/// A payload migrated by `Migrate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Record<P> {
+    /// The payload was already at the target version and is returned unchanged.
+    Current(P),
+    /// The payload was re-encoded at the target version, with the version embedded.
+    Migrated(Vec<u8>),
+}
+
+/// The outcome of migrating a batch, with records identified by their index in the input.
+#[derive(Debug, Default)]
+pub struct Summary {
+    /// Records that were re-encoded and need to be written back.
+    pub migrated: Vec<(usize, Vec<u8>)>,
+    /// Number of records already at the target version.
+    pub current: usize,
+    /// Records that failed to migrate.
+    pub errors: Vec<(usize, Error)>,
+}
+
+impl Summary {
+    fn push<P>(&mut self, index: usize, record: Result<Record<P>>) {
+        match record {
+            Ok(Record::Current(_)) => self.current += 1,
+            Ok(Record::Migrated(payload)) => self.migrated.push((index, payload)),
+            Err(err) => self.errors.push((index, err)),
+        }
+    }
+}
+
+/// Iterator adapter returned by `migrate`.
+///
+/// Yields one result per payload, so a payload that fails to migrate does not stop the batch.
+pub struct Migrate<T, I> {
+    payloads: I,
+    version: u16,
+    _marker: PhantomData<fn() -> T>,
+}
+
+/// Migrates each embedded-version payload in `payloads` to `version`. Payloads already at
+/// `version` are passed through without being decoded.
+pub fn migrate<T, I>(payloads: I, version: u16) -> Migrate<T, I::IntoIter>
+where
+    T: OwnedVersionedData,
+    I: IntoIterator,
+    I::Item: AsRef<[u8]>,
+{
+    Migrate {
+        payloads: payloads.into_iter(),
+        version,
+        _marker: PhantomData,
+    }
+}
+
+impl<T, I> Migrate<T, I>
+where
+    T: OwnedVersionedData,
+    I: Iterator,
+    I::Item: AsRef<[u8]>,
+{
+    /// Migrates the remaining payloads, collecting the results and errors.
+    pub fn summarize(self) -> Summary {
+        let mut summary = Summary::default();
+        for (index, record) in self.enumerate() {
+            summary.push(index, record);
+        }
+        summary
+    }
+}
+
+impl<T, I> Iterator for Migrate<T, I>
+where
+    T: OwnedVersionedData,
+    I: Iterator,
+    I::Item: AsRef<[u8]>,
+{
+    type Item = Result<Record<I::Item>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let payload = self.payloads.next()?;
+        Some(match migrate_one::<T>(payload.as_ref(), self.version) {
+            Ok(Some(migrated)) => Ok(Record::Migrated(migrated)),
+            Ok(None) => Ok(Record::Current(payload)),
+            Err(err) => Err(err),
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.payloads.size_hint()
+    }
+}
+
+impl<T, I: fmt::Debug> fmt::Debug for Migrate<T, I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Migrate")
+            .field("payloads", &self.payloads)
+            .field("version", &self.version)
+            .finish()
+    }
+}
+
+/// Migrates the embedded-version payloads in parallel on the rayon thread pool. Payloads already
+/// at `version` are not decoded.
+#[cfg(feature = "rayon")]
+pub fn par_migrate<T, P>(payloads: &[P], version: u16) -> Summary
+where
+    T: OwnedVersionedData,
+    P: AsRef<[u8]> + Sync,
+{
+    use rayon::prelude::*;
+
+    let records: Vec<_> = payloads
+        .par_iter()
+        .map(|payload| migrate_one::<T>(payload.as_ref(), version))
+        .collect();
+
+    let mut summary = Summary::default();
+    for (index, record) in records.into_iter().enumerate() {
+        summary.push(
+            index,
+            record.map(|migrated| match migrated {
+                Some(migrated) => Record::Migrated(migrated),
+                None => Record::Current(()),
+            }),
+        );
+    }
+    summary
+}
+
+/// Returns the payload re-encoded at `version`, or `None` if it is already at `version`.
+fn migrate_one<T: OwnedVersionedData>(payload: &[u8], version: u16) -> Result<Option<Vec<u8>>> {
+    match T::transcode_with_embedded_version(payload, version)? {
+        Cow::Borrowed(_) => Ok(None),
+        Cow::Owned(migrated) => Ok(Some(migrated)),
+    }
+}
@@ -0,0 +1,111 @@
+//! `tokio_util::codec` integration using the length-delimited frame format from `framing`.
+
+use core::marker::PhantomData;
+
+use bytes::{Buf, BufMut, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::framing::{frame_header, frame_len, parse_frame, Frame, DEFAULT_MAX_FRAME_LENGTH};
+use crate::{Error, OwnedVersionedData, Result};
+
+/// Codec for using `T` with `tokio_util::codec::Framed`.
+///
+/// Inbound frames are decoded from whatever version they embed and converted to the latest
+/// version. Outbound messages are converted to and encoded at the configured target version.
+///
+/// As with `framing::FrameDecoder`, a frame is consumed before its payload is deserialized, so a
+/// payload that fails to decode or convert does not desync the stream.
+pub struct VbareCodec<T> {
+    version: u16,
+    max_frame_length: usize,
+    _marker: PhantomData<fn(T) -> T>,
+}
+
+impl<T: OwnedVersionedData> VbareCodec<T> {
+    /// Creates a codec that encodes outbound messages at `version`.
+    pub fn new(version: u16) -> Self {
+        Self {
+            version,
+            max_frame_length: DEFAULT_MAX_FRAME_LENGTH,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn with_max_frame_length(mut self, max_frame_length: usize) -> Self {
+        self.max_frame_length = max_frame_length;
+        self
+    }
+
+    /// Version outbound messages are encoded at.
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// Changes the version outbound messages are encoded at, e.g. after negotiating with a peer.
+    pub fn set_version(&mut self, version: u16) {
+        self.version = version;
+    }
+
+    pub fn max_frame_length(&self) -> usize {
+        self.max_frame_length
+    }
+}
+
+impl<T> Clone for VbareCodec<T> {
+    fn clone(&self) -> Self {
+        Self {
+            version: self.version,
+            max_frame_length: self.max_frame_length,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> core::fmt::Debug for VbareCodec<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("VbareCodec")
+            .field("version", &self.version)
+            .field("max_frame_length", &self.max_frame_length)
+            .finish()
+    }
+}
+
+impl<T: OwnedVersionedData> Decoder for VbareCodec<T> {
+    type Item = T::Latest;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<T::Latest>> {
+        let frame = match parse_frame(src, self.max_frame_length) {
+            Ok(Some(frame)) => frame,
+            Ok(None) => return Ok(None),
+            Err(Error::TruncatedHeader) => {
+                src.advance(frame_len(src));
+                return Err(Error::TruncatedHeader);
+            }
+            Err(err) => return Err(err),
+        };
+
+        let Frame {
+            version,
+            payload,
+            len,
+        } = frame;
+        let result = T::deserialize(payload, version);
+        src.advance(len);
+
+        result.map(Some)
+    }
+}
+
+impl<T: OwnedVersionedData> Encoder<T::Latest> for VbareCodec<T> {
+    type Error = Error;
+
+    fn encode(&mut self, item: T::Latest, dst: &mut BytesMut) -> Result<()> {
+        let payload = T::wrap_latest(item).serialize(self.version)?;
+        let header = frame_header(self.version, payload.len(), self.max_frame_length)?;
+        dst.reserve(header.len() + payload.len());
+        dst.put_slice(&header);
+        dst.put_slice(&payload);
+        Ok(())
+    }
+}
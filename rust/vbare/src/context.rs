@@ -0,0 +1,235 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{
+    check_version, migration, observe, Error, MigrationStep, OwnedVersionedData, Result,
+    VersionedData,
+};
+
+/// Converts data between two adjacent versions using a caller-supplied context. Variants of other
+/// versions are passed through unchanged.
+pub type ContextConverter<T, Ctx> = fn(T, &mut Ctx) -> anyhow::Result<T>;
+
+/// A `Shortcut` whose converter receives the context.
+pub struct ContextShortcut<T, Ctx: ?Sized> {
+    pub from: u16,
+    pub to: u16,
+    pub convert: ContextConverter<T, Ctx>,
+}
+
+impl<T, Ctx: ?Sized> Clone for ContextShortcut<T, Ctx> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, Ctx: ?Sized> Copy for ContextShortcut<T, Ctx> {}
+
+impl<T, Ctx: ?Sized> fmt::Debug for ContextShortcut<T, Ctx> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ContextShortcut")
+            .field("from", &self.from)
+            .field("to", &self.to)
+            .finish()
+    }
+}
+
+impl<T, Ctx: ?Sized> migration::Hop for ContextShortcut<T, Ctx> {
+    type Convert = ContextConverter<T, Ctx>;
+
+    fn hop(&self) -> (u16, u16, ContextConverter<T, Ctx>) {
+        (self.from, self.to, self.convert)
+    }
+}
+
+/// `VersionedData` with converters that receive a caller-supplied context, such as the current
+/// time or an ID allocator.
+///
+/// Context converters and shortcuts are matched up with versions and planned like the converters of
+/// `VersionedData`, which `deserialize_with_context` and `serialize_with_context` do not use.
+pub trait ContextVersionedData<'a, Ctx: ?Sized + 'a>: VersionedData<'a> {
+    /// Converters from each version to the next, ending with the one that produces
+    /// `LATEST_VERSION`. See `VersionedData::deserialize_converters`.
+    fn deserialize_context_converters() -> &'a [ContextConverter<Self, Ctx>] {
+        &[]
+    }
+
+    /// Converters from each version to the previous, starting with the one that takes
+    /// `LATEST_VERSION`. See `VersionedData::serialize_converters`.
+    fn serialize_context_converters() -> &'a [ContextConverter<Self, Ctx>] {
+        &[]
+    }
+
+    // See VersionedData::deserialize_shortcuts.
+    fn deserialize_context_shortcuts() -> &'a [ContextShortcut<Self, Ctx>] {
+        &[]
+    }
+
+    // See VersionedData::serialize_shortcuts.
+    fn serialize_context_shortcuts() -> &'a [ContextShortcut<Self, Ctx>] {
+        &[]
+    }
+
+    // See VersionedData::deserialize.
+    fn deserialize_with_context(
+        payload: &'a [u8],
+        version: u16,
+        ctx: &mut Ctx,
+    ) -> Result<Self::Latest> {
+        let _span = observe::deserialize(
+            core::any::type_name::<Self>(),
+            version,
+            Self::LATEST_VERSION,
+        );
+
+        check_version(version, Self::supported_versions())?;
+
+        let path = migration::upgrade(
+            version,
+            Self::LATEST_VERSION,
+            Self::deserialize_context_converters(),
+            Self::deserialize_context_shortcuts(),
+        )?;
+
+        let mut data = Self::deserialize_version(payload, version)
+            .map_err(|source| Error::Decode { version, source })?;
+
+        for (step, convert) in path {
+            data = run_step(data, step, convert, ctx)?;
+        }
+
+        data.unwrap_latest()
+            .map_err(|source| Error::NotLatest { source })
+    }
+
+    // See VersionedData::serialize.
+    fn serialize_with_context(self, version: u16, ctx: &mut Ctx) -> Result<Vec<u8>> {
+        let _span = observe::serialize(
+            core::any::type_name::<Self>(),
+            version,
+            Self::LATEST_VERSION,
+        );
+
+        check_version(version, Self::supported_versions())?;
+
+        let path = migration::downgrade(
+            Self::LATEST_VERSION,
+            Self::LATEST_VERSION,
+            version,
+            Self::serialize_context_converters(),
+            Self::serialize_context_shortcuts(),
+        )?;
+
+        let mut data = self;
+        for (step, convert) in path {
+            data = run_step(data, step, convert, ctx)?;
+        }
+
+        Self::serialize_version(data, version).map_err(|source| Error::Encode { version, source })
+    }
+}
+
+/// `OwnedVersionedData` with converters that receive a caller-supplied context. See
+/// `ContextVersionedData`.
+pub trait ContextOwnedVersionedData<Ctx: ?Sized + 'static>: OwnedVersionedData {
+    // See ContextVersionedData::deserialize_context_converters.
+    fn deserialize_context_converters() -> &'static [ContextConverter<Self, Ctx>] {
+        &[]
+    }
+
+    // See ContextVersionedData::serialize_context_converters.
+    fn serialize_context_converters() -> &'static [ContextConverter<Self, Ctx>] {
+        &[]
+    }
+
+    // See VersionedData::deserialize_shortcuts.
+    fn deserialize_context_shortcuts() -> &'static [ContextShortcut<Self, Ctx>] {
+        &[]
+    }
+
+    // See VersionedData::serialize_shortcuts.
+    fn serialize_context_shortcuts() -> &'static [ContextShortcut<Self, Ctx>] {
+        &[]
+    }
+
+    // See VersionedData::deserialize.
+    fn deserialize_with_context(
+        payload: &[u8],
+        version: u16,
+        ctx: &mut Ctx,
+    ) -> Result<Self::Latest> {
+        let _span = observe::deserialize(
+            core::any::type_name::<Self>(),
+            version,
+            Self::LATEST_VERSION,
+        );
+
+        check_version(version, Self::supported_versions())?;
+
+        let path = migration::upgrade(
+            version,
+            Self::LATEST_VERSION,
+            Self::deserialize_context_converters(),
+            Self::deserialize_context_shortcuts(),
+        )?;
+
+        let mut data = Self::deserialize_version(payload, version)
+            .map_err(|source| Error::Decode { version, source })?;
+
+        for (step, convert) in path {
+            data = run_step(data, step, convert, ctx)?;
+        }
+
+        data.unwrap_latest()
+            .map_err(|source| Error::NotLatest { source })
+    }
+
+    // See VersionedData::serialize.
+    fn serialize_with_context(self, version: u16, ctx: &mut Ctx) -> Result<Vec<u8>> {
+        serialize(self, version, ctx, run_step)
+    }
+}
+
+/// Downgrades `data` to `version` through the serialize context converters and encodes it. `run`
+/// performs each step, so callers can inspect the context around it.
+pub(crate) fn serialize<T, Ctx>(
+    data: T,
+    version: u16,
+    ctx: &mut Ctx,
+    mut run: impl FnMut(T, MigrationStep, ContextConverter<T, Ctx>, &mut Ctx) -> Result<T>,
+) -> Result<Vec<u8>>
+where
+    T: ContextOwnedVersionedData<Ctx>,
+    Ctx: ?Sized + 'static,
+{
+    let _span = observe::serialize(core::any::type_name::<T>(), version, T::LATEST_VERSION);
+
+    check_version(version, T::supported_versions())?;
+
+    let path = migration::downgrade(
+        T::LATEST_VERSION,
+        T::LATEST_VERSION,
+        version,
+        T::serialize_context_converters(),
+        T::serialize_context_shortcuts(),
+    )?;
+
+    let mut data = data;
+    for (step, convert) in path {
+        data = run(data, step, convert, ctx)?;
+    }
+
+    T::serialize_version(data, version).map_err(|source| Error::Encode { version, source })
+}
+
+/// Runs one context converter through `observe::step`.
+pub(crate) fn run_step<T, Ctx: ?Sized>(
+    data: T,
+    step: MigrationStep,
+    convert: ContextConverter<T, Ctx>,
+    ctx: &mut Ctx,
+) -> Result<T> {
+    observe::step(core::any::type_name::<T>(), step, data, |data| {
+        convert(data, ctx)
+    })
+}
@@ -0,0 +1,200 @@
+//! Formats for storing the version alongside a payload.
+//!
+//! `Embedded` is the two-byte little-endian header written by `serialize_with_embedded_version`.
+//! Other envelopes can be used through `serialize_with_envelope` and `deserialize_with_envelope`.
+
+use alloc::{borrow::Cow, vec::Vec};
+
+use crate::{peek_version, Error, Result};
+
+#[cfg(any(feature = "zstd", feature = "lz4"))]
+pub use compressed::{Codec, Compressed};
+#[cfg(feature = "encryption")]
+pub use encrypted::Encrypted;
+
+#[cfg(any(feature = "zstd", feature = "lz4"))]
+mod compressed;
+#[cfg(feature = "encryption")]
+mod encrypted;
+
+/// Wraps an encoded payload with its version.
+pub trait Envelope {
+    /// Returns the message holding `payload` encoded at `version`.
+    fn seal(&self, version: u16, payload: &[u8]) -> Result<Vec<u8>>;
+
+    /// Returns the version and payload held by `message`.
+    fn open<'m>(&self, message: &'m [u8]) -> Result<(u16, Cow<'m, [u8]>)>;
+}
+
+impl<E: Envelope + ?Sized> Envelope for &E {
+    fn seal(&self, version: u16, payload: &[u8]) -> Result<Vec<u8>> {
+        (**self).seal(version, payload)
+    }
+
+    fn open<'m>(&self, message: &'m [u8]) -> Result<(u16, Cow<'m, [u8]>)> {
+        (**self).open(message)
+    }
+}
+
+/// The version as a little-endian `u16`, followed by the payload.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Embedded;
+
+impl Envelope for Embedded {
+    fn seal(&self, version: u16, payload: &[u8]) -> Result<Vec<u8>> {
+        let mut message = Vec::with_capacity(2 + payload.len());
+        message.extend_from_slice(&version.to_le_bytes());
+        message.extend_from_slice(payload);
+        Ok(message)
+    }
+
+    fn open<'m>(&self, message: &'m [u8]) -> Result<(u16, Cow<'m, [u8]>)> {
+        let version = peek_version(message)?;
+        Ok((version, Cow::Borrowed(&message[2..])))
+    }
+}
+
+/// A fixed magic number identifying the format, then the `Embedded` header and payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Magic(pub &'static [u8]);
+
+impl Envelope for Magic {
+    fn seal(&self, version: u16, payload: &[u8]) -> Result<Vec<u8>> {
+        let mut message = Vec::with_capacity(self.0.len() + 2 + payload.len());
+        message.extend_from_slice(self.0);
+        message.extend_from_slice(&version.to_le_bytes());
+        message.extend_from_slice(payload);
+        Ok(message)
+    }
+
+    fn open<'m>(&self, message: &'m [u8]) -> Result<(u16, Cow<'m, [u8]>)> {
+        let Some(rest) = message.strip_prefix(self.0) else {
+            return Err(if message.len() < self.0.len() {
+                Error::TruncatedHeader
+            } else {
+                Error::BadMagic
+            });
+        };
+        Embedded.open(rest)
+    }
+}
+
+/// The version as an unsigned LEB128 varint, followed by the payload. Versions below 128 take a
+/// single byte.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Varint;
+
+impl Envelope for Varint {
+    fn seal(&self, version: u16, payload: &[u8]) -> Result<Vec<u8>> {
+        let mut message = Vec::with_capacity(3 + payload.len());
+        let mut rest = version;
+        while rest >= 0x80 {
+            message.push((rest as u8 & 0x7f) | 0x80);
+            rest >>= 7;
+        }
+        message.push(rest as u8);
+        message.extend_from_slice(payload);
+        Ok(message)
+    }
+
+    fn open<'m>(&self, message: &'m [u8]) -> Result<(u16, Cow<'m, [u8]>)> {
+        let mut version = 0u32;
+        for (i, byte) in message.iter().enumerate().take(3) {
+            version |= u32::from(byte & 0x7f) << (7 * i);
+            if byte & 0x80 == 0 {
+                let version = u16::try_from(version).map_err(|_| Error::InvalidVarint)?;
+                return Ok((version, Cow::Borrowed(&message[i + 1..])));
+            }
+        }
+
+        Err(if message.len() < 3 {
+            Error::TruncatedHeader
+        } else {
+            Error::InvalidVarint
+        })
+    }
+}
+
+/// A little-endian `u32` schema identifier, then the `Embedded` header and payload. Opening a
+/// message written for another schema fails with `Error::SchemaMismatch`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SchemaId(pub u32);
+
+impl Envelope for SchemaId {
+    fn seal(&self, version: u16, payload: &[u8]) -> Result<Vec<u8>> {
+        let mut message = Vec::with_capacity(6 + payload.len());
+        message.extend_from_slice(&self.0.to_le_bytes());
+        message.extend_from_slice(&version.to_le_bytes());
+        message.extend_from_slice(payload);
+        Ok(message)
+    }
+
+    fn open<'m>(&self, message: &'m [u8]) -> Result<(u16, Cow<'m, [u8]>)> {
+        let [a, b, c, d, rest @ ..] = message else {
+            return Err(Error::TruncatedHeader);
+        };
+        let got = u32::from_le_bytes([*a, *b, *c, *d]);
+        if got != self.0 {
+            return Err(Error::SchemaMismatch {
+                expected: self.0,
+                got,
+            });
+        }
+        Embedded.open(rest)
+    }
+}
+
+/// Another envelope followed by a little-endian CRC32C of its header and payload, to detect
+/// corruption of data at rest. Opening a corrupted message fails with `Error::ChecksumMismatch`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Checksummed<E = Embedded>(pub E);
+
+impl<E: Envelope> Envelope for Checksummed<E> {
+    fn seal(&self, version: u16, payload: &[u8]) -> Result<Vec<u8>> {
+        let mut message = self.0.seal(version, payload)?;
+        let checksum = crc32c(&message);
+        message.extend_from_slice(&checksum.to_le_bytes());
+        Ok(message)
+    }
+
+    fn open<'m>(&self, message: &'m [u8]) -> Result<(u16, Cow<'m, [u8]>)> {
+        let Some((message, checksum)) = message.split_last_chunk::<4>() else {
+            return Err(Error::TruncatedHeader);
+        };
+
+        let expected = u32::from_le_bytes(*checksum);
+        let got = crc32c(message);
+        if got != expected {
+            return Err(Error::ChecksumMismatch { expected, got });
+        }
+
+        self.0.open(message)
+    }
+}
+
+/// CRC-32C (Castagnoli) lookup table for the reflected polynomial.
+const CRC32C_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0x82f6_3b78
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+fn crc32c(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, byte| {
+        CRC32C_TABLE[((crc ^ u32::from(*byte)) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
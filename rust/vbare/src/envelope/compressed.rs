@@ -0,0 +1,195 @@
+use alloc::{borrow::Cow, vec::Vec};
+
+use super::{Embedded, Envelope};
+use crate::{Error, Result};
+
+const UNCOMPRESSED: u8 = 0;
+#[cfg(feature = "zstd")]
+const ZSTD: u8 = 1;
+#[cfg(feature = "lz4")]
+const LZ4: u8 = 2;
+
+/// Compression algorithm used by `Compressed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Codec {
+    /// zstd at the given compression level. Requires the `zstd` feature.
+    #[cfg(feature = "zstd")]
+    Zstd { level: i32 },
+    /// LZ4 block format. Requires the `lz4` feature.
+    #[cfg(feature = "lz4")]
+    Lz4,
+}
+
+impl Codec {
+    fn id(self) -> u8 {
+        match self {
+            #[cfg(feature = "zstd")]
+            Codec::Zstd { .. } => ZSTD,
+            #[cfg(feature = "lz4")]
+            Codec::Lz4 => LZ4,
+        }
+    }
+
+    fn compress(self, payload: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            #[cfg(feature = "zstd")]
+            Codec::Zstd { level } => zstd::bulk::compress(payload, level)
+                .map_err(|err| Error::Compression { source: err.into() }),
+            #[cfg(feature = "lz4")]
+            Codec::Lz4 => Ok(lz4_flex::compress_prepend_size(payload)),
+        }
+    }
+}
+
+/// Decompresses `body`, failing with `Error::DecompressedTooLarge` instead of producing more than
+/// `max` bytes.
+fn decompress(codec: u8, body: &[u8], max: usize) -> Result<Vec<u8>> {
+    let too_large = |len: u64| Error::DecompressedTooLarge {
+        len: usize::try_from(len).unwrap_or(usize::MAX),
+        max,
+    };
+
+    let payload = match codec {
+        #[cfg(feature = "zstd")]
+        ZSTD => {
+            // The frame records its decompressed size unless it was written by a streaming encoder
+            match zstd::zstd_safe::get_frame_content_size(body) {
+                Ok(Some(len)) if len > max as u64 => return Err(too_large(len)),
+                Ok(Some(len)) => {
+                    zstd::bulk::decompress(body, len as usize).map_err(anyhow::Error::from)
+                }
+                // Decode without reserving `max` bytes up front, stopping one byte past the limit
+                Ok(None) => {
+                    use std::io::Read;
+
+                    let mut payload = Vec::new();
+                    let read = zstd::stream::Decoder::with_buffer(body)
+                        .and_then(|decoder| decoder.take(max as u64 + 1).read_to_end(&mut payload));
+                    match read {
+                        Ok(len) if len > max => return Err(too_large(len as u64)),
+                        Ok(_) => Ok(payload),
+                        Err(err) => Err(err.into()),
+                    }
+                }
+                Err(_) => {
+                    return Err(Error::Compression {
+                        source: anyhow::anyhow!("invalid zstd frame header"),
+                    })
+                }
+            }
+        }
+        #[cfg(feature = "lz4")]
+        LZ4 => {
+            if let Some(len) = body.first_chunk::<4>().map(|len| u32::from_le_bytes(*len)) {
+                if len as usize > max {
+                    return Err(too_large(len.into()));
+                }
+            }
+            lz4_flex::decompress_size_prepended(body).map_err(anyhow::Error::from)
+        }
+        _ => return Err(Error::UnknownCodec { codec }),
+    };
+    payload.map_err(|source| Error::Compression { source })
+}
+
+/// Another envelope holding a codec byte followed by the payload, compressed if it is at least
+/// `threshold` bytes long.
+///
+/// Opening decompresses with whichever codec the message was written with, as long as its feature
+/// is enabled. Compressed payloads are decoded into a new buffer, so borrowed data can only be
+/// deserialized from payloads below the threshold. Payloads that would decompress to more than
+/// `max_len` bytes are rejected before they are decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compressed<E = Embedded> {
+    envelope: E,
+    codec: Codec,
+    threshold: usize,
+    max_len: usize,
+}
+
+impl<E> Compressed<E> {
+    /// Payload length compression starts at unless configured with `with_threshold`.
+    pub const DEFAULT_THRESHOLD: usize = 256;
+
+    /// Limit on the length of a decompressed payload unless configured with `with_max_len`.
+    pub const DEFAULT_MAX_LEN: usize = 16 * 1024 * 1024;
+
+    pub fn new(envelope: E, codec: Codec) -> Self {
+        Compressed {
+            envelope,
+            codec,
+            threshold: Self::DEFAULT_THRESHOLD,
+            max_len: Self::DEFAULT_MAX_LEN,
+        }
+    }
+
+    /// Sets the payload length compression starts at.
+    pub fn with_threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Sets the longest payload opening may decompress to.
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+}
+
+impl<E: Envelope> Envelope for Compressed<E> {
+    fn seal(&self, version: u16, payload: &[u8]) -> Result<Vec<u8>> {
+        let compressed = if payload.len() >= self.threshold {
+            Some(self.codec.compress(payload)?)
+        } else {
+            None
+        };
+
+        // Payloads that don't shrink are stored as is
+        let mut body = Vec::with_capacity(1 + payload.len());
+        match compressed {
+            Some(compressed) if compressed.len() < payload.len() => {
+                body.push(self.codec.id());
+                body.extend_from_slice(&compressed);
+            }
+            _ => {
+                body.push(UNCOMPRESSED);
+                body.extend_from_slice(payload);
+            }
+        }
+
+        self.envelope.seal(version, &body)
+    }
+
+    fn open<'m>(&self, message: &'m [u8]) -> Result<(u16, Cow<'m, [u8]>)> {
+        let (version, body) = self.envelope.open(message)?;
+        let payload = match body {
+            Cow::Borrowed([UNCOMPRESSED, payload @ ..]) => Cow::Borrowed(payload),
+            Cow::Borrowed([codec, body @ ..]) => {
+                Cow::Owned(decompress(*codec, body, self.max_len)?)
+            }
+            Cow::Owned(mut body) => match body.first() {
+                Some(&UNCOMPRESSED) => {
+                    body.remove(0);
+                    Cow::Owned(body)
+                }
+                Some(&codec) => Cow::Owned(decompress(codec, &body[1..], self.max_len)?),
+                None => return Err(Error::TruncatedHeader),
+            },
+            Cow::Borrowed([]) => return Err(Error::TruncatedHeader),
+        };
+        Ok((version, payload))
+    }
+}
@@ -0,0 +1,108 @@
+use alloc::{borrow::Cow, collections::BTreeMap, vec::Vec};
+use core::fmt;
+
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    XChaCha20Poly1305, XNonce,
+};
+
+use super::Envelope;
+use crate::{Error, Result};
+
+const HEADER_LEN: usize = 6;
+const NONCE_LEN: usize = 24;
+const TAG_LEN: usize = 16;
+
+/// The version as a little-endian `u16` and the id of the key as a little-endian `u32`, followed
+/// by a random nonce and the payload encrypted with XChaCha20-Poly1305.
+///
+/// The version and key id are authenticated as associated data, so a message cannot be replayed
+/// under a different version. Messages are sealed with the current key, and opened with whichever
+/// key they name, so old keys can be kept with `with_key` while data is re-encrypted.
+#[derive(Clone)]
+pub struct Encrypted {
+    key_id: u32,
+    keys: BTreeMap<u32, XChaCha20Poly1305>,
+}
+
+impl Encrypted {
+    /// Creates an envelope that seals messages with `key`, identified by `key_id`.
+    pub fn new(key_id: u32, key: &[u8; 32]) -> Self {
+        let mut keys = BTreeMap::new();
+        keys.insert(key_id, XChaCha20Poly1305::new(key.into()));
+        Encrypted { key_id, keys }
+    }
+
+    /// Adds a key that is only used to open messages, such as a key being rotated out.
+    pub fn with_key(mut self, key_id: u32, key: &[u8; 32]) -> Self {
+        if key_id != self.key_id {
+            self.keys.insert(key_id, XChaCha20Poly1305::new(key.into()));
+        }
+        self
+    }
+
+    /// Id of the key new messages are sealed with.
+    pub fn key_id(&self) -> u32 {
+        self.key_id
+    }
+}
+
+impl fmt::Debug for Encrypted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Encrypted")
+            .field("key_id", &self.key_id)
+            .field("key_ids", &self.keys.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Envelope for Encrypted {
+    fn seal(&self, version: u16, payload: &[u8]) -> Result<Vec<u8>> {
+        let cipher = &self.keys[&self.key_id];
+
+        let mut header = [0u8; HEADER_LEN];
+        header[..2].copy_from_slice(&version.to_le_bytes());
+        header[2..].copy_from_slice(&self.key_id.to_le_bytes());
+
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: payload,
+                    aad: &header,
+                },
+            )
+            .map_err(|_| Error::Encryption)?;
+
+        let mut message = Vec::with_capacity(HEADER_LEN + NONCE_LEN + ciphertext.len());
+        message.extend_from_slice(&header);
+        message.extend_from_slice(&nonce);
+        message.extend_from_slice(&ciphertext);
+        Ok(message)
+    }
+
+    fn open<'m>(&self, message: &'m [u8]) -> Result<(u16, Cow<'m, [u8]>)> {
+        if message.len() < HEADER_LEN + NONCE_LEN + TAG_LEN {
+            return Err(Error::TruncatedHeader);
+        }
+
+        let (header, rest) = message.split_at(HEADER_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        let version = u16::from_le_bytes([header[0], header[1]]);
+        let key_id = u32::from_le_bytes([header[2], header[3], header[4], header[5]]);
+
+        let cipher = self.keys.get(&key_id).ok_or(Error::UnknownKey { key_id })?;
+        let payload = cipher
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: header,
+                },
+            )
+            .map_err(|_| Error::Decryption { key_id })?;
+
+        Ok((version, Cow::Owned(payload)))
+    }
+}
@@ -0,0 +1,204 @@
+use core::fmt;
+
+use crate::{Loss, VersionRange};
+
+pub type Result<T, E = Error> = core::result::Result<T, E>;
+
+/// Errors returned by the provided methods of `VersionedData` and `OwnedVersionedData`.
+///
+/// Failures from user-implemented methods (`deserialize_version`, `serialize_version`,
+/// `unwrap_latest` and converters) are wrapped with the version context they occurred in, with the
+/// original error available as the source. `Error` converts into `anyhow::Error` with `?`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Version 0 was requested. Versions start at 1.
+    ZeroVersion,
+    /// The version is newer than the latest version this type knows about.
+    UnsupportedVersion { got: u16, latest: u16 },
+    /// The version is older than the oldest version this type still supports.
+    VersionTooOld { got: u16, min: u16 },
+    /// The payload is too short to contain the embedded version header.
+    TruncatedHeader,
+    /// `deserialize_version` failed to decode the payload.
+    Decode { version: u16, source: anyhow::Error },
+    /// `serialize_version` failed to encode the data.
+    Encode { version: u16, source: anyhow::Error },
+    /// A converter between two adjacent versions failed.
+    Convert {
+        from: u16,
+        to: u16,
+        source: anyhow::Error,
+    },
+    /// There is no chain of converters and shortcuts between two versions.
+    NoMigrationPath { from: u16, to: u16 },
+    /// More converters were given than there are steps between version 1 and the latest version.
+    TooManyConverters { latest: u16, got: usize },
+    /// A shortcut does not move towards the latest version when deserializing, or away from it
+    /// when serializing, or refers to a version that does not exist.
+    InvalidShortcut { from: u16, to: u16 },
+    /// The converter chain did not produce the latest version.
+    NotLatest { source: anyhow::Error },
+    /// A length-delimited frame exceeds the configured maximum length.
+    FrameTooLarge { len: usize, max: usize },
+    /// A version range is empty or starts at version 0.
+    InvalidVersionRange { min: u16, max: u16 },
+    /// The local and remote peers do not support any version in common.
+    NoCommonVersion {
+        local: VersionRange,
+        remote: VersionRange,
+    },
+    /// A strict serialize dropped data the requested version cannot represent.
+    LossyDowngrade(Loss),
+    /// The message does not start with the magic number of its `envelope::Magic`.
+    BadMagic,
+    /// The message was written for a different `envelope::SchemaId`.
+    SchemaMismatch { expected: u32, got: u32 },
+    /// A varint version header is longer than three bytes or exceeds `u16::MAX`.
+    InvalidVarint,
+    /// The checksum stored in an `envelope::Checksummed` message does not match its contents.
+    ChecksumMismatch { expected: u32, got: u32 },
+    /// A compressed message uses a codec that is unknown or whose feature is not enabled.
+    UnknownCodec { codec: u8 },
+    /// Compressing or decompressing a payload failed.
+    Compression { source: anyhow::Error },
+    /// A compressed payload would decompress to more than the configured maximum length. `len` is
+    /// `max + 1` if the payload does not record its decompressed length.
+    DecompressedTooLarge { len: usize, max: usize },
+    /// Encrypting a payload failed.
+    Encryption,
+    /// A message was encrypted with a key that is not known to `envelope::Encrypted`.
+    UnknownKey { key_id: u32 },
+    /// A message failed to decrypt, because it was corrupted, tampered with or encrypted with a
+    /// different key.
+    Decryption { key_id: u32 },
+    /// An envelope decoded the payload into a new buffer, which `VersionedData` cannot borrow
+    /// from.
+    PayloadNotBorrowed { version: u16 },
+    /// `set_observer` was called after an observer was installed.
+    ObserverAlreadySet,
+    /// Reading from or writing to a stream failed.
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ZeroVersion => write!(f, "proto version (0) must be > 0"),
+            Error::UnsupportedVersion { got, latest } => write!(
+                f,
+                "proto version ({got}) greater than latest version ({latest})"
+            ),
+            Error::VersionTooOld { got, min } => write!(
+                f,
+                "proto version ({got}) less than minimum supported version ({min})"
+            ),
+            Error::TruncatedHeader => write!(f, "payload too short for embedded version"),
+            Error::Decode { version, .. } => write!(f, "failed to decode version {version}"),
+            Error::Encode { version, .. } => write!(f, "failed to encode version {version}"),
+            Error::Convert { from, to, .. } => {
+                write!(f, "failed to convert version {from} to version {to}")
+            }
+            Error::NoMigrationPath { from, to } => {
+                write!(f, "no converters from version {from} to version {to}")
+            }
+            Error::TooManyConverters { latest, got } => write!(
+                f,
+                "{got} converters given for latest version ({latest}), expected at most {}",
+                latest.saturating_sub(1)
+            ),
+            Error::InvalidShortcut { from, to } => {
+                write!(f, "invalid shortcut from version {from} to version {to}")
+            }
+            Error::NotLatest { .. } => write!(f, "converted data is not the latest version"),
+            Error::FrameTooLarge { len, max } => {
+                write!(
+                    f,
+                    "frame length ({len}) exceeds maximum frame length ({max})"
+                )
+            }
+            Error::InvalidVersionRange { min, max } => {
+                write!(f, "invalid version range ({min}..={max})")
+            }
+            Error::NoCommonVersion { local, remote } => write!(
+                f,
+                "no common version between local versions ({local}) and remote versions ({remote})"
+            ),
+            Error::LossyDowngrade(loss) => write!(f, "{loss}"),
+            Error::ObserverAlreadySet => write!(f, "migration observer already set"),
+            Error::BadMagic => write!(f, "message does not start with the expected magic number"),
+            Error::SchemaMismatch { expected, got } => {
+                write!(
+                    f,
+                    "schema id ({got}) does not match expected schema id ({expected})"
+                )
+            }
+            Error::InvalidVarint => write!(f, "invalid varint version header"),
+            Error::ChecksumMismatch { expected, got } => write!(
+                f,
+                "checksum ({got:#010x}) does not match stored checksum ({expected:#010x})"
+            ),
+            Error::UnknownCodec { codec } => write!(f, "unknown compression codec ({codec})"),
+            Error::Compression { .. } => write!(f, "failed to compress or decompress payload"),
+            Error::DecompressedTooLarge { len, max } => write!(
+                f,
+                "decompressed payload length ({len}) exceeds maximum decompressed length ({max})"
+            ),
+            Error::Encryption => write!(f, "failed to encrypt payload"),
+            Error::UnknownKey { key_id } => write!(f, "unknown encryption key ({key_id})"),
+            Error::Decryption { key_id } => {
+                write!(f, "failed to decrypt payload with key ({key_id})")
+            }
+            Error::PayloadNotBorrowed { version } => write!(
+                f,
+                "cannot deserialize version {version} from a payload decoded by the envelope"
+            ),
+            #[cfg(feature = "std")]
+            Error::Io(_) => write!(f, "io error"),
+        }
+    }
+}
+
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Error::Decode { source, .. }
+            | Error::Encode { source, .. }
+            | Error::Convert { source, .. }
+            | Error::NotLatest { source }
+            | Error::Compression { source } => Some(source.as_ref()),
+            #[cfg(feature = "std")]
+            Error::Io(err) => Some(err),
+            Error::ZeroVersion
+            | Error::UnsupportedVersion { .. }
+            | Error::VersionTooOld { .. }
+            | Error::TruncatedHeader
+            | Error::NoMigrationPath { .. }
+            | Error::TooManyConverters { .. }
+            | Error::InvalidShortcut { .. }
+            | Error::FrameTooLarge { .. }
+            | Error::InvalidVersionRange { .. }
+            | Error::NoCommonVersion { .. }
+            | Error::LossyDowngrade(_)
+            | Error::ObserverAlreadySet
+            | Error::BadMagic
+            | Error::SchemaMismatch { .. }
+            | Error::InvalidVarint
+            | Error::ChecksumMismatch { .. }
+            | Error::UnknownCodec { .. }
+            | Error::DecompressedTooLarge { .. }
+            | Error::Encryption
+            | Error::UnknownKey { .. }
+            | Error::Decryption { .. }
+            | Error::PayloadNotBorrowed { .. } => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
@@ -0,0 +1,203 @@
+//! Length-delimited framing for streams of embedded-version messages.
+//!
+//! Each frame is a little-endian `u32` length followed by that many bytes of embedded-version
+//! message (see `OwnedVersionedData::serialize_with_embedded_version`):
+//!
+//! ```text
+//! +-------------------+-------------------+-------------------+
+//! |   Frame Length    |  Schema Version   |    BARE Payload   |
+//! |   (uint32, 4B)    |   (uint16, 2B)    |   (variable N B)  |
+//! +-------------------+-------------------+-------------------+
+//! ```
+//!
+//! The frame length covers the version and the payload.
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use crate::{Error, OwnedVersionedData, Result};
+
+/// Size of the length prefix in bytes.
+pub const LENGTH_PREFIX_LEN: usize = 4;
+
+/// Default limit on the length of a single frame, excluding the length prefix.
+pub const DEFAULT_MAX_FRAME_LENGTH: usize = 8 * 1024 * 1024;
+
+/// A single frame borrowed from an input buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame<'a> {
+    /// Schema version embedded in the frame.
+    pub version: u16,
+    /// BARE payload encoded at `version`.
+    pub payload: &'a [u8],
+    /// Total number of bytes the frame occupies, including the length prefix.
+    pub len: usize,
+}
+
+/// Builds the length prefix and version header for a frame with a payload of `payload_len` bytes.
+pub fn frame_header(
+    version: u16,
+    payload_len: usize,
+    max_frame_length: usize,
+) -> Result<[u8; LENGTH_PREFIX_LEN + 2]> {
+    let len = 2 + payload_len;
+    if len > max_frame_length || len > u32::MAX as usize {
+        return Err(Error::FrameTooLarge {
+            len,
+            max: max_frame_length,
+        });
+    }
+
+    let [l0, l1, l2, l3] = (len as u32).to_le_bytes();
+    let [v0, v1] = version.to_le_bytes();
+    Ok([l0, l1, l2, l3, v0, v1])
+}
+
+/// Appends a frame holding `payload` encoded at `version` to `dst`.
+pub fn write_frame(
+    version: u16,
+    payload: &[u8],
+    max_frame_length: usize,
+    dst: &mut Vec<u8>,
+) -> Result<()> {
+    let header = frame_header(version, payload.len(), max_frame_length)?;
+    dst.reserve(header.len() + payload.len());
+    dst.extend_from_slice(&header);
+    dst.extend_from_slice(payload);
+    Ok(())
+}
+
+/// Parses the frame at the start of `src`.
+///
+/// Returns `None` if `src` does not contain a whole frame yet. A frame too short to hold a version
+/// fails with `Error::TruncatedHeader` once all of it has arrived, so the caller can skip its
+/// `frame_len` bytes.
+pub fn parse_frame(src: &[u8], max_frame_length: usize) -> Result<Option<Frame<'_>>> {
+    let Some(prefix) = src.get(..LENGTH_PREFIX_LEN) else {
+        return Ok(None);
+    };
+
+    let len = u32::from_le_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]) as usize;
+    if len > max_frame_length {
+        return Err(Error::FrameTooLarge {
+            len,
+            max: max_frame_length,
+        });
+    }
+
+    let Some(body) = src.get(LENGTH_PREFIX_LEN..LENGTH_PREFIX_LEN + len) else {
+        return Ok(None);
+    };
+    if len < 2 {
+        return Err(Error::TruncatedHeader);
+    }
+
+    Ok(Some(Frame {
+        version: u16::from_le_bytes([body[0], body[1]]),
+        payload: &body[2..],
+        len: LENGTH_PREFIX_LEN + len,
+    }))
+}
+
+/// Length of the frame at the start of `src`, including its length prefix. `src` must hold at
+/// least the length prefix.
+pub fn frame_len(src: &[u8]) -> usize {
+    let prefix = [src[0], src[1], src[2], src[3]];
+    LENGTH_PREFIX_LEN + u32::from_le_bytes(prefix) as usize
+}
+
+/// Encodes `T` values as frames.
+pub struct FrameEncoder<T> {
+    max_frame_length: usize,
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<T: OwnedVersionedData> FrameEncoder<T> {
+    pub fn new() -> Self {
+        Self::with_max_frame_length(DEFAULT_MAX_FRAME_LENGTH)
+    }
+
+    pub fn with_max_frame_length(max_frame_length: usize) -> Self {
+        Self {
+            max_frame_length,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Serializes `data` at `version` and appends it to `dst` as a frame.
+    pub fn encode(&self, data: T, version: u16, dst: &mut Vec<u8>) -> Result<()> {
+        let payload = data.serialize(version)?;
+        write_frame(version, &payload, self.max_frame_length, dst)
+    }
+}
+
+impl<T: OwnedVersionedData> Default for FrameEncoder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Incrementally decodes frames into the latest version of `T`.
+///
+/// Feed bytes as they arrive with `extend` and call `decode` until it returns `Ok(None)`.
+/// A frame is consumed before its payload is deserialized, so a payload that fails to decode or
+/// convert, or a frame too short to hold a version, does not stop the stream. `Error::FrameTooLarge` is not recoverable since the frame
+/// boundary is lost.
+pub struct FrameDecoder<T> {
+    buf: Vec<u8>,
+    max_frame_length: usize,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: OwnedVersionedData> FrameDecoder<T> {
+    pub fn new() -> Self {
+        Self::with_max_frame_length(DEFAULT_MAX_FRAME_LENGTH)
+    }
+
+    pub fn with_max_frame_length(max_frame_length: usize) -> Self {
+        Self {
+            buf: Vec::new(),
+            max_frame_length,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Buffers bytes read from the underlying stream.
+    pub fn extend(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Number of buffered bytes that have not been decoded yet.
+    pub fn buffered_len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Decodes the next buffered frame, or returns `None` if a whole frame has not arrived yet.
+    pub fn decode(&mut self) -> Result<Option<T::Latest>> {
+        let frame = match parse_frame(&self.buf, self.max_frame_length) {
+            Ok(Some(frame)) => frame,
+            Ok(None) => return Ok(None),
+            Err(Error::TruncatedHeader) => {
+                self.buf.drain(..frame_len(&self.buf));
+                return Err(Error::TruncatedHeader);
+            }
+            Err(err) => return Err(err),
+        };
+
+        let Frame {
+            version,
+            payload,
+            len,
+        } = frame;
+        let result = T::deserialize(payload, version);
+        self.buf.drain(..len);
+
+        result.map(Some)
+    }
+}
+
+impl<T: OwnedVersionedData> Default for FrameDecoder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,172 +1,656 @@
-use anyhow::{bail, Context, Result};
+#![cfg_attr(not(feature = "std"), no_std)]
 
-pub trait VersionedData<'a>: Sized {
+extern crate alloc;
+
+use alloc::{borrow::Cow, vec::Vec};
+
+pub use async_data::{AsyncConverter, AsyncOwnedVersionedData};
+pub use context::{
+    ContextConverter, ContextOwnedVersionedData, ContextShortcut, ContextVersionedData,
+};
+pub use envelope::Envelope;
+pub use error::{Error, Result};
+pub use migration::{DeserializeInfo, MigrationStep, Shortcut};
+#[cfg(feature = "std")]
+pub use observe::set_observer;
+pub use observe::MigrationObserver;
+pub use report::{DowngradeReport, Loss, ReportingOwnedVersionedData};
+pub use version::VersionRange;
+
+mod async_data;
+pub mod batch;
+#[cfg(feature = "tokio")]
+pub mod codec;
+mod context;
+pub mod envelope;
+mod error;
+pub mod framing;
+mod migration;
+mod observe;
+mod report;
+pub mod session;
+mod version;
+
+#[cfg(feature = "derive")]
+pub use vbare_derive::{OwnedVersionedData, VersionedData};
+
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::vec::Vec;
+    pub use anyhow;
+    #[cfg(feature = "derive")]
+    pub use serde_bare;
+}
+
+/// Converts data between two adjacent versions. Variants of other versions are passed through
+/// unchanged.
+pub type Converter<T> = fn(T) -> anyhow::Result<T>;
+
+pub trait VersionedData<'a>: Sized + 'a {
     type Latest;
 
+    /// Latest version, which `Latest` is the schema of.
+    const LATEST_VERSION: u16;
+
+    /// Oldest version `deserialize` and `serialize` accept. Older versions fail with
+    /// `Error::VersionTooOld`, which allows sunsetting old clients without removing their variants.
+    const MIN_SUPPORTED_VERSION: u16 = 1;
+
     fn wrap_latest(latest: Self::Latest) -> Self;
-    fn unwrap_latest(self) -> Result<Self::Latest>;
-    fn deserialize_version(payload: &'a [u8], version: u16) -> Result<Self>;
-    fn serialize_version(self, version: u16) -> Result<Vec<u8>>;
+    fn unwrap_latest(self) -> anyhow::Result<Self::Latest>;
+    fn deserialize_version(payload: &'a [u8], version: u16) -> anyhow::Result<Self>;
+    fn serialize_version(self, version: u16) -> anyhow::Result<Vec<u8>>;
 
-    fn deserialize_converters() -> Vec<impl Fn(Self) -> Result<Self>> {
-        // HACK: We do this for the type checker
-        if false {
-            vec![identity]
-        } else {
-            Vec::new()
-        }
+    /// Encodes the data like `serialize_version` without consuming it, or returns `None` if it
+    /// can only be encoded by value. Lets `serialize_ref` skip cloning when no converters run.
+    fn serialize_version_ref(&self, version: u16) -> Option<anyhow::Result<Vec<u8>>> {
+        let _ = version;
+        None
+    }
+
+    /// Converters from each version to the next, ending with the one that produces
+    /// `LATEST_VERSION`.
+    ///
+    /// Converters are matched up starting from the latest version, so converters for versions
+    /// below `MIN_SUPPORTED_VERSION` may be left out.
+    fn deserialize_converters() -> &'a [Converter<Self>] {
+        &[]
+    }
+
+    /// Converters from each version to the previous, starting with the one that takes
+    /// `LATEST_VERSION`.
+    fn serialize_converters() -> &'a [Converter<Self>] {
+        &[]
     }
 
-    fn serialize_converters() -> Vec<impl Fn(Self) -> Result<Self>> {
-        // HACK: We do this for the type checker
-        if false {
-            vec![identity]
-        } else {
-            Vec::new()
+    /// Converters that skip over intermediate versions when deserializing, such as from version 2
+    /// straight to version 7. `deserialize` takes the path with the fewest steps.
+    fn deserialize_shortcuts() -> &'a [Shortcut<Self>] {
+        &[]
+    }
+
+    /// Converters that skip over intermediate versions when serializing. `serialize` takes the
+    /// path with the fewest steps.
+    fn serialize_shortcuts() -> &'a [Shortcut<Self>] {
+        &[]
+    }
+
+    /// Steps `deserialize` takes to convert `version` to the latest version.
+    fn upgrade_path(version: u16) -> Result<Vec<MigrationStep>> {
+        check_version(version, Self::supported_versions())?;
+        let path = migration::upgrade(
+            version,
+            Self::LATEST_VERSION,
+            Self::deserialize_converters(),
+            Self::deserialize_shortcuts(),
+        )?;
+        Ok(path.map(|(step, _)| step).collect())
+    }
+
+    /// Steps `serialize` takes to convert the latest version to `version`.
+    fn downgrade_path(version: u16) -> Result<Vec<MigrationStep>> {
+        check_version(version, Self::supported_versions())?;
+        let path = migration::downgrade(
+            Self::LATEST_VERSION,
+            Self::LATEST_VERSION,
+            version,
+            Self::serialize_converters(),
+            Self::serialize_shortcuts(),
+        )?;
+        Ok(path.map(|(step, _)| step).collect())
+    }
+
+    /// Versions `deserialize` and `serialize` accept.
+    fn supported_versions() -> VersionRange {
+        VersionRange {
+            min: Self::MIN_SUPPORTED_VERSION,
+            max: Self::LATEST_VERSION,
         }
     }
 
     fn deserialize(payload: &'a [u8], version: u16) -> Result<Self::Latest> {
-        let mut data = Self::deserialize_version(payload, version)?;
+        Self::deserialize_with_info(payload, version).map(|(latest, _)| latest)
+    }
 
-        let skip_count = version
-            .checked_sub(1)
-            .with_context(|| format!("proto version ({version}) must be > 0"))?;
-        for converter in Self::deserialize_converters()
-            .iter()
-            .skip(skip_count as usize)
-        {
-            data = converter(data)?;
+    /// Deserializes like `deserialize`, also returning the version the payload was stored at and
+    /// whether it should be rewritten at the latest version.
+    fn deserialize_with_info(
+        payload: &'a [u8],
+        version: u16,
+    ) -> Result<(Self::Latest, DeserializeInfo)> {
+        let type_name = core::any::type_name::<Self>();
+        let _span = observe::deserialize(type_name, version, Self::LATEST_VERSION);
+
+        check_version(version, Self::supported_versions())?;
+
+        let path = migration::upgrade(
+            version,
+            Self::LATEST_VERSION,
+            Self::deserialize_converters(),
+            Self::deserialize_shortcuts(),
+        )?;
+
+        let mut data = Self::deserialize_version(payload, version)
+            .map_err(|source| Error::Decode { version, source })?;
+
+        let mut steps = 0;
+        for (step, convert) in path {
+            data = observe::step(type_name, step, data, convert)?;
+            steps += 1;
         }
 
-        data.unwrap_latest()
+        let latest = data
+            .unwrap_latest()
+            .map_err(|source| Error::NotLatest { source })?;
+        let info = DeserializeInfo {
+            version,
+            steps,
+            needs_rewrite: version != Self::LATEST_VERSION,
+        };
+        Ok((latest, info))
     }
 
     fn serialize(self, version: u16) -> Result<Vec<u8>> {
+        let type_name = core::any::type_name::<Self>();
+        let _span = observe::serialize(type_name, version, Self::LATEST_VERSION);
+
+        check_version(version, Self::supported_versions())?;
+
+        let path = migration::downgrade(
+            Self::LATEST_VERSION,
+            Self::LATEST_VERSION,
+            version,
+            Self::serialize_converters(),
+            Self::serialize_shortcuts(),
+        )?;
+
         let mut data = self;
+        for (step, convert) in path {
+            data = observe::step(type_name, step, data, convert)?;
+        }
+
+        Self::serialize_version(data, version).map_err(|source| Error::Encode { version, source })
+    }
 
-        let converters = Self::serialize_converters();
-        let take_count = (converters.len() + 1)
-            .checked_sub(version as usize)
-            .with_context(|| {
-                format!(
-                    "proto version ({version}) greater than latest version ({})",
-                    converters.len() + 1
-                )
-            })?;
-        for converter in converters.iter().take(take_count) {
-            data = converter(data)?;
+    /// Serializes data without consuming it. At the latest version the data is encoded from the
+    /// borrow if `serialize_version_ref` supports it, otherwise it is cloned and passed to
+    /// `serialize`.
+    fn serialize_ref(&self, version: u16) -> Result<Vec<u8>>
+    where
+        Self: Clone,
+    {
+        if version == Self::LATEST_VERSION {
+            if let Some(payload) = self.serialize_version_ref(version) {
+                let _span = observe::serialize(core::any::type_name::<Self>(), version, version);
+                return payload.map_err(|source| Error::Encode { version, source });
+            }
         }
 
-        Self::serialize_version(data, version)
+        self.clone().serialize(version)
     }
 
-    /// Serializes data with the version encoded as the first byte.
+    /// Deserializes data with the version encoded as the first two bytes.
     fn deserialize_with_embedded_version(payload: &'a [u8]) -> Result<Self::Latest> {
-        if payload.len() < 2 {
-            bail!("payload too short for embedded version");
+        Self::deserialize_with_embedded_version_info(payload).map(|(latest, _)| latest)
+    }
+
+    /// Deserializes like `deserialize_with_embedded_version`, also returning the embedded version
+    /// and whether the payload should be rewritten at the latest version.
+    fn deserialize_with_embedded_version_info(
+        payload: &'a [u8],
+    ) -> Result<(Self::Latest, DeserializeInfo)> {
+        let version = peek_version(payload)?;
+        Self::deserialize_with_info(&payload[2..], version)
+    }
+
+    /// Serializes data with the version encoded as the first two bytes.
+    fn serialize_with_embedded_version(self, version: u16) -> Result<Vec<u8>> {
+        self.serialize_with_envelope(version, envelope::Embedded)
+    }
+
+    /// Converts a payload encoded at `from` to `to`. The payload is returned unchanged if the
+    /// versions match, otherwise it is deserialized to the latest version and serialized at `to`.
+    fn transcode(payload: &'a [u8], from: u16, to: u16) -> Result<Cow<'a, [u8]>> {
+        if from == to {
+            check_version(from, Self::supported_versions())?;
+            return Ok(Cow::Borrowed(payload));
         }
 
-        let version = u16::from_le_bytes([payload[0], payload[1]]);
-        let payload = &payload[2..];
+        let latest = Self::deserialize(payload, from)?;
+        Self::wrap_latest(latest).serialize(to).map(Cow::Owned)
+    }
 
-        Self::deserialize(payload, version)
+    /// Converts a payload with an embedded version to `to`, like `transcode`.
+    fn transcode_with_embedded_version(payload: &'a [u8], to: u16) -> Result<Cow<'a, [u8]>> {
+        let from = peek_version(payload)?;
+        if from == to {
+            check_version(from, Self::supported_versions())?;
+            return Ok(Cow::Borrowed(payload));
+        }
+
+        let latest = Self::deserialize(&payload[2..], from)?;
+        Self::wrap_latest(latest)
+            .serialize_with_embedded_version(to)
+            .map(Cow::Owned)
     }
 
-    /// Deserializes data with the version encoded as the first byte.
-    fn serialize_with_embedded_version(self, version: u16) -> Result<Vec<u8>> {
+    /// Serializes data into a message in the format of `envelope`.
+    fn serialize_with_envelope<E: Envelope>(self, version: u16, envelope: E) -> Result<Vec<u8>> {
         let payload = self.serialize(version)?;
-        let mut result = Vec::with_capacity(2 + payload.len());
-        result.extend_from_slice(&version.to_le_bytes());
-        result.extend_from_slice(&payload);
-        Ok(result)
+        envelope.seal(version, &payload)
+    }
+
+    /// Deserializes a message in the format of `envelope`.
+    ///
+    /// Fails with `Error::PayloadNotBorrowed` if the envelope decodes the payload into a new
+    /// buffer, such as by decompressing it, since the data could not borrow from it.
+    fn deserialize_with_envelope<E: Envelope>(
+        message: &'a [u8],
+        envelope: E,
+    ) -> Result<Self::Latest> {
+        match envelope.open(message)? {
+            (version, Cow::Borrowed(payload)) => Self::deserialize(payload, version),
+            (version, Cow::Owned(_)) => Err(Error::PayloadNotBorrowed { version }),
+        }
     }
 }
 
-pub trait OwnedVersionedData: Sized {
+pub trait OwnedVersionedData: Sized + 'static {
     type Latest;
 
+    // See VersionedData::LATEST_VERSION.
+    const LATEST_VERSION: u16;
+
+    // See VersionedData::MIN_SUPPORTED_VERSION.
+    const MIN_SUPPORTED_VERSION: u16 = 1;
+
     fn wrap_latest(latest: Self::Latest) -> Self;
-    fn unwrap_latest(self) -> Result<Self::Latest>;
-    fn deserialize_version(payload: &[u8], version: u16) -> Result<Self>;
-    fn serialize_version(self, version: u16) -> Result<Vec<u8>>;
+    fn unwrap_latest(self) -> anyhow::Result<Self::Latest>;
+    fn deserialize_version(payload: &[u8], version: u16) -> anyhow::Result<Self>;
+    fn serialize_version(self, version: u16) -> anyhow::Result<Vec<u8>>;
 
-    fn deserialize_converters() -> Vec<impl Fn(Self) -> Result<Self>> {
-        // HACK: We do this for the type checker
-        if false {
-            vec![identity]
-        } else {
-            Vec::new()
-        }
+    // See VersionedData::serialize_version_ref.
+    fn serialize_version_ref(&self, version: u16) -> Option<anyhow::Result<Vec<u8>>> {
+        let _ = version;
+        None
+    }
+
+    // See VersionedData::deserialize_converters.
+    fn deserialize_converters() -> &'static [Converter<Self>] {
+        &[]
+    }
+
+    // See VersionedData::serialize_converters.
+    fn serialize_converters() -> &'static [Converter<Self>] {
+        &[]
+    }
+
+    // See VersionedData::deserialize_shortcuts.
+    fn deserialize_shortcuts() -> &'static [Shortcut<Self>] {
+        &[]
+    }
+
+    // See VersionedData::serialize_shortcuts.
+    fn serialize_shortcuts() -> &'static [Shortcut<Self>] {
+        &[]
+    }
+
+    // See VersionedData::upgrade_path.
+    fn upgrade_path(version: u16) -> Result<Vec<MigrationStep>> {
+        check_version(version, Self::supported_versions())?;
+        let path = migration::upgrade(
+            version,
+            Self::LATEST_VERSION,
+            Self::deserialize_converters(),
+            Self::deserialize_shortcuts(),
+        )?;
+        Ok(path.map(|(step, _)| step).collect())
     }
 
-    fn serialize_converters() -> Vec<impl Fn(Self) -> Result<Self>> {
-        // HACK: We do this for the type checker
-        if false {
-            vec![identity]
-        } else {
-            Vec::new()
+    // See VersionedData::downgrade_path.
+    fn downgrade_path(version: u16) -> Result<Vec<MigrationStep>> {
+        check_version(version, Self::supported_versions())?;
+        let path = migration::downgrade(
+            Self::LATEST_VERSION,
+            Self::LATEST_VERSION,
+            version,
+            Self::serialize_converters(),
+            Self::serialize_shortcuts(),
+        )?;
+        Ok(path.map(|(step, _)| step).collect())
+    }
+
+    // See VersionedData::supported_versions.
+    fn supported_versions() -> VersionRange {
+        VersionRange {
+            min: Self::MIN_SUPPORTED_VERSION,
+            max: Self::LATEST_VERSION,
         }
     }
 
     fn deserialize(payload: &[u8], version: u16) -> Result<Self::Latest> {
-        let mut data = Self::deserialize_version(payload, version)?;
+        Self::deserialize_with_info(payload, version).map(|(latest, _)| latest)
+    }
+
+    // See VersionedData::deserialize_with_info.
+    fn deserialize_with_info(
+        payload: &[u8],
+        version: u16,
+    ) -> Result<(Self::Latest, DeserializeInfo)> {
+        let type_name = core::any::type_name::<Self>();
+        let _span = observe::deserialize(type_name, version, Self::LATEST_VERSION);
 
-        let skip_count = version
-            .checked_sub(1)
-            .with_context(|| format!("proto version ({version}) must be > 0"))?;
-        for converter in Self::deserialize_converters()
-            .iter()
-            .skip(skip_count as usize)
-        {
-            data = converter(data)?;
+        check_version(version, Self::supported_versions())?;
+
+        let path = migration::upgrade(
+            version,
+            Self::LATEST_VERSION,
+            Self::deserialize_converters(),
+            Self::deserialize_shortcuts(),
+        )?;
+
+        let mut data = Self::deserialize_version(payload, version)
+            .map_err(|source| Error::Decode { version, source })?;
+
+        let mut steps = 0;
+        for (step, convert) in path {
+            data = observe::step(type_name, step, data, convert)?;
+            steps += 1;
         }
 
-        data.unwrap_latest()
+        let latest = data
+            .unwrap_latest()
+            .map_err(|source| Error::NotLatest { source })?;
+        let info = DeserializeInfo {
+            version,
+            steps,
+            needs_rewrite: version != Self::LATEST_VERSION,
+        };
+        Ok((latest, info))
     }
 
     fn serialize(self, version: u16) -> Result<Vec<u8>> {
+        let type_name = core::any::type_name::<Self>();
+        let _span = observe::serialize(type_name, version, Self::LATEST_VERSION);
+
+        check_version(version, Self::supported_versions())?;
+
+        let path = migration::downgrade(
+            Self::LATEST_VERSION,
+            Self::LATEST_VERSION,
+            version,
+            Self::serialize_converters(),
+            Self::serialize_shortcuts(),
+        )?;
+
         let mut data = self;
+        for (step, convert) in path {
+            data = observe::step(type_name, step, data, convert)?;
+        }
+
+        Self::serialize_version(data, version).map_err(|source| Error::Encode { version, source })
+    }
 
-        let converters = Self::serialize_converters();
-        let take_count = (converters.len() + 1)
-            .checked_sub(version as usize)
-            .with_context(|| {
-                format!(
-                    "proto version ({version}) greater than latest version ({})",
-                    converters.len() + 1
-                )
-            })?;
-        for converter in converters.iter().take(take_count) {
-            data = converter(data)?;
+    // See VersionedData::serialize_ref.
+    fn serialize_ref(&self, version: u16) -> Result<Vec<u8>>
+    where
+        Self: Clone,
+    {
+        if version == Self::LATEST_VERSION {
+            if let Some(payload) = self.serialize_version_ref(version) {
+                let _span = observe::serialize(core::any::type_name::<Self>(), version, version);
+                return payload.map_err(|source| Error::Encode { version, source });
+            }
         }
 
-        Self::serialize_version(data, version)
+        self.clone().serialize(version)
     }
 
     // See VersionedData::deserialize_with_embedded_version.
     fn deserialize_with_embedded_version(payload: &[u8]) -> Result<Self::Latest> {
-        if payload.len() < 2 {
-            bail!("payload too short for embedded version");
-        }
-
-        let version = u16::from_le_bytes([payload[0], payload[1]]);
-        let payload = &payload[2..];
+        Self::deserialize_with_embedded_version_info(payload).map(|(latest, _)| latest)
+    }
 
-        Self::deserialize(payload, version)
+    // See VersionedData::deserialize_with_info.
+    fn deserialize_with_embedded_version_info(
+        payload: &[u8],
+    ) -> Result<(Self::Latest, DeserializeInfo)> {
+        let version = peek_version(payload)?;
+        Self::deserialize_with_info(&payload[2..], version)
     }
 
     // See VersionedData::serialize_with_embedded_version.
     fn serialize_with_embedded_version(self, version: u16) -> Result<Vec<u8>> {
+        self.serialize_with_envelope(version, envelope::Embedded)
+    }
+
+    // See VersionedData::transcode.
+    fn transcode(payload: &[u8], from: u16, to: u16) -> Result<Cow<'_, [u8]>> {
+        if from == to {
+            check_version(from, Self::supported_versions())?;
+            return Ok(Cow::Borrowed(payload));
+        }
+
+        let latest = Self::deserialize(payload, from)?;
+        Self::wrap_latest(latest).serialize(to).map(Cow::Owned)
+    }
+
+    // See VersionedData::transcode_with_embedded_version.
+    fn transcode_with_embedded_version(payload: &[u8], to: u16) -> Result<Cow<'_, [u8]>> {
+        let from = peek_version(payload)?;
+        if from == to {
+            check_version(from, Self::supported_versions())?;
+            return Ok(Cow::Borrowed(payload));
+        }
+
+        let latest = Self::deserialize(&payload[2..], from)?;
+        Self::wrap_latest(latest)
+            .serialize_with_embedded_version(to)
+            .map(Cow::Owned)
+    }
+
+    // See VersionedData::serialize_with_envelope.
+    fn serialize_with_envelope<E: Envelope>(self, version: u16, envelope: E) -> Result<Vec<u8>> {
         let payload = self.serialize(version)?;
-        let mut result = Vec::with_capacity(2 + payload.len());
-        result.extend_from_slice(&version.to_le_bytes());
-        result.extend_from_slice(&payload);
-        Ok(result)
+        envelope.seal(version, &payload)
+    }
+
+    // See VersionedData::deserialize_with_envelope.
+    fn deserialize_with_envelope<E: Envelope>(message: &[u8], envelope: E) -> Result<Self::Latest> {
+        let (version, payload) = envelope.open(message)?;
+        Self::deserialize(&payload, version)
+    }
+
+    /// Serializes data at each of `versions`, planning the path to the oldest version once and
+    /// encoding each requested version on the way.
+    ///
+    /// Every payload matches what `serialize` produces. Requested versions that the path skips
+    /// over, such as ones a shortcut jumps past, are serialized separately from a clone. The data
+    /// is also cloned at every requested version on the path except the oldest, since
+    /// `serialize_version` consumes it.
+    #[cfg(feature = "std")]
+    fn serialize_many(self, versions: &[u16]) -> Result<std::collections::HashMap<u16, Vec<u8>>>
+    where
+        Self: Clone,
+    {
+        let type_name = core::any::type_name::<Self>();
+
+        let mut versions = versions.to_vec();
+        for &version in &versions {
+            check_version(version, Self::supported_versions())?;
+        }
+        versions.sort_unstable_by(|a, b| b.cmp(a));
+        versions.dedup();
+
+        let mut payloads = std::collections::HashMap::with_capacity(versions.len());
+        let Some(&oldest) = versions.last() else {
+            return Ok(payloads);
+        };
+
+        // Steps only move towards older versions, so the path to each version on the way to the
+        // oldest is the same one `serialize` plans for it.
+        let path: Vec<_> = migration::downgrade(
+            Self::LATEST_VERSION,
+            Self::LATEST_VERSION,
+            oldest,
+            Self::serialize_converters(),
+            Self::serialize_shortcuts(),
+        )?
+        .collect();
+        let (on_path, skipped): (Vec<u16>, Vec<u16>) = versions.iter().partition(|&&version| {
+            version == Self::LATEST_VERSION || path.iter().any(|(step, _)| step.to == version)
+        });
+
+        for version in skipped {
+            payloads.insert(version, self.clone().serialize(version)?);
+        }
+
+        let mut steps = path.into_iter();
+        let mut downgrade = |mut data: Self, from: u16, version: u16| -> Result<Self> {
+            let _span = observe::serialize(type_name, version, from);
+            if from == version {
+                return Ok(data);
+            }
+            for (step, convert) in steps.by_ref() {
+                data = observe::step(type_name, step, data, convert)?;
+                if step.to == version {
+                    break;
+                }
+            }
+            Ok(data)
+        };
+
+        let Some((&last, rest)) = on_path.split_last() else {
+            return Ok(payloads);
+        };
+
+        let mut data = self;
+        let mut from = Self::LATEST_VERSION;
+        for &version in rest {
+            data = downgrade(data, from, version)?;
+            let payload = data
+                .clone()
+                .serialize_version(version)
+                .map_err(|source| Error::Encode { version, source })?;
+            payloads.insert(version, payload);
+            from = version;
+        }
+
+        let data = downgrade(data, from, last)?;
+        let payload = Self::serialize_version(data, last).map_err(|source| Error::Encode {
+            version: last,
+            source,
+        })?;
+        payloads.insert(last, payload);
+
+        Ok(payloads)
+    }
+
+    /// Serializes data with the version embedded, encrypted with the current key of `keys`. See
+    /// `envelope::Encrypted`.
+    #[cfg(feature = "encryption")]
+    fn encrypt_with_embedded_version(
+        self,
+        version: u16,
+        keys: &envelope::Encrypted,
+    ) -> Result<Vec<u8>> {
+        self.serialize_with_envelope(version, keys)
+    }
+
+    /// Decrypts and deserializes data written by `encrypt_with_embedded_version`.
+    #[cfg(feature = "encryption")]
+    fn decrypt_with_embedded_version(
+        message: &[u8],
+        keys: &envelope::Encrypted,
+    ) -> Result<Self::Latest> {
+        Self::deserialize_with_envelope(message, keys)
+    }
+
+    /// Serializes data into a writer without a version header.
+    #[cfg(feature = "std")]
+    fn serialize_into<W: std::io::Write>(self, version: u16, mut writer: W) -> Result<()> {
+        let payload = self.serialize(version)?;
+        writer.write_all(&payload)?;
+        Ok(())
+    }
+
+    /// Serializes data into a writer with the version encoded as the first two bytes.
+    #[cfg(feature = "std")]
+    fn serialize_with_embedded_version_into<W: std::io::Write>(
+        self,
+        version: u16,
+        mut writer: W,
+    ) -> Result<()> {
+        let payload = self.serialize(version)?;
+        writer.write_all(&version.to_le_bytes())?;
+        writer.write_all(&payload)?;
+        Ok(())
+    }
+
+    /// Deserializes data from a reader with the version encoded as the first two bytes.
+    ///
+    /// The payload is read until EOF, so the reader must contain exactly one message.
+    #[cfg(feature = "std")]
+    fn deserialize_from<R: std::io::Read>(mut reader: R) -> Result<Self::Latest> {
+        let mut header = [0u8; 2];
+        reader.read_exact(&mut header).map_err(|err| {
+            if err.kind() == std::io::ErrorKind::UnexpectedEof {
+                Error::TruncatedHeader
+            } else {
+                Error::Io(err)
+            }
+        })?;
+        let version = u16::from_le_bytes(header);
+
+        let mut payload = Vec::new();
+        reader.read_to_end(&mut payload)?;
+
+        Self::deserialize(&payload, version)
     }
 }
 
-/// Helper for default trait methods.
-fn identity<T>(v: T) -> Result<T> {
-    Ok(v)
+/// Reads the version embedded in the first two bytes of a payload without decoding it.
+pub fn peek_version(payload: &[u8]) -> Result<u16> {
+    match payload {
+        [low, high, ..] => Ok(u16::from_le_bytes([*low, *high])),
+        _ => Err(Error::TruncatedHeader),
+    }
+}
+
+fn check_version(version: u16, supported: VersionRange) -> Result<()> {
+    if version == 0 {
+        Err(Error::ZeroVersion)
+    } else if version < supported.min {
+        Err(Error::VersionTooOld {
+            got: version,
+            min: supported.min,
+        })
+    } else if version > supported.max {
+        Err(Error::UnsupportedVersion {
+            got: version,
+            latest: supported.max,
+        })
+    } else {
+        Ok(())
+    }
 }
@@ -0,0 +1,287 @@
+//! Planning which converters run between two versions.
+//!
+//! Versions form a graph where each adjacent converter connects a version to the next one, and
+//! shortcuts connect versions further apart, e.g. version 2 straight to version 7. `deserialize`
+//! and `serialize` take the path with the fewest steps. Ties are broken in favor of adjacent
+//! converters, then shortcuts in the order they are listed.
+
+use alloc::{vec, vec::Vec};
+use core::{convert::Infallible, fmt, marker::PhantomData};
+
+use crate::{Converter, Error, Result};
+
+/// A converter that skips over the versions between `from` and `to`.
+///
+/// Like adjacent converters, it must pass variants of other versions through unchanged.
+pub struct Shortcut<T> {
+    pub from: u16,
+    pub to: u16,
+    pub convert: Converter<T>,
+}
+
+impl<T> Clone for Shortcut<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Shortcut<T> {}
+
+impl<T> fmt::Debug for Shortcut<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Shortcut")
+            .field("from", &self.from)
+            .field("to", &self.to)
+            .finish()
+    }
+}
+
+/// A shortcut whose converter is of type `Convert`, so paths can be planned for every kind of
+/// converter.
+pub(crate) trait Hop {
+    type Convert: Copy;
+
+    fn hop(&self) -> (u16, u16, Self::Convert);
+}
+
+impl<T> Hop for Shortcut<T> {
+    type Convert = Converter<T>;
+
+    fn hop(&self) -> (u16, u16, Converter<T>) {
+        (self.from, self.to, self.convert)
+    }
+}
+
+/// Stands in for the shortcuts of converters that do not support them.
+pub(crate) struct NoShortcut<C>(Infallible, PhantomData<C>);
+
+impl<C: Copy> Hop for NoShortcut<C> {
+    type Convert = C;
+
+    fn hop(&self) -> (u16, u16, C) {
+        match self.0 {}
+    }
+}
+
+/// A single conversion on a migration path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MigrationStep {
+    pub from: u16,
+    pub to: u16,
+}
+
+/// How a payload was upgraded by `deserialize_with_info`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeserializeInfo {
+    /// Version the payload was encoded at.
+    pub version: u16,
+    /// Number of converters and shortcuts run to reach the latest version.
+    pub steps: usize,
+    /// True if the payload is older than the latest version, so storing it again would avoid
+    /// migrating it on every read.
+    pub needs_rewrite: bool,
+}
+
+/// Converters to run in order, along with the step each one performs.
+pub(crate) enum Path<'c, C> {
+    /// Adjacent converters only, one version at a time.
+    Linear {
+        converters: &'c [C],
+        from: u16,
+        upgrade: bool,
+    },
+    Planned(vec::IntoIter<(MigrationStep, C)>),
+}
+
+impl<C: Copy> Iterator for Path<'_, C> {
+    type Item = (MigrationStep, C);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Path::Linear {
+                converters,
+                from,
+                upgrade,
+            } => {
+                let (convert, rest) = converters.split_first()?;
+                let to = if *upgrade { *from + 1 } else { *from - 1 };
+                let step = MigrationStep { from: *from, to };
+                *converters = rest;
+                *from = to;
+                Some((step, *convert))
+            }
+            Path::Planned(steps) => steps.next(),
+        }
+    }
+}
+
+/// Plans the path from `version` to `latest` through deserialize converters and shortcuts.
+///
+/// Converters are matched up starting from the latest version, so the first one converts
+/// `latest - converters.len()`.
+pub(crate) fn upgrade<'c, S: Hop>(
+    version: u16,
+    latest: u16,
+    converters: &'c [S::Convert],
+    shortcuts: &[S],
+) -> Result<Path<'c, S::Convert>> {
+    check_converters(latest, converters)?;
+    let first = latest - converters.len() as u16;
+
+    if shortcuts.is_empty() {
+        if version < first {
+            return Err(Error::NoMigrationPath {
+                from: version,
+                to: latest,
+            });
+        }
+
+        return Ok(Path::Linear {
+            converters: &converters[(version - first) as usize..(latest - first) as usize],
+            from: version,
+            upgrade: true,
+        });
+    }
+
+    if let Some((from, to, _)) = shortcuts
+        .iter()
+        .map(Hop::hop)
+        .find(|&(from, to, _)| from >= to || from == 0 || to > latest)
+    {
+        return Err(Error::InvalidShortcut { from, to });
+    }
+
+    let adjacent = |v: u16| {
+        (v >= first && v < latest)
+            .then(|| converters.get((v - first) as usize))
+            .flatten()
+            .map(|convert| (v + 1, *convert))
+    };
+    shortest_path(version, latest, true, adjacent, shortcuts)
+}
+
+/// Plans the path from `from` to `version` through serialize converters and shortcuts.
+///
+/// Converters are matched up starting from the latest version, so the first one converts `latest`.
+pub(crate) fn downgrade<'c, S: Hop>(
+    latest: u16,
+    from: u16,
+    version: u16,
+    converters: &'c [S::Convert],
+    shortcuts: &[S],
+) -> Result<Path<'c, S::Convert>> {
+    check_converters(latest, converters)?;
+
+    if shortcuts.is_empty() {
+        let Some(converters) =
+            converters.get((latest - from) as usize..(latest - version) as usize)
+        else {
+            return Err(Error::NoMigrationPath { from, to: version });
+        };
+
+        return Ok(Path::Linear {
+            converters,
+            from,
+            upgrade: false,
+        });
+    }
+
+    if let Some((from, to, _)) = shortcuts
+        .iter()
+        .map(Hop::hop)
+        .find(|&(from, to, _)| from <= to || to == 0 || from > latest)
+    {
+        return Err(Error::InvalidShortcut { from, to });
+    }
+
+    let adjacent = |v: u16| {
+        (v > 1)
+            .then(|| converters.get((latest - v) as usize))
+            .flatten()
+            .map(|convert| (v - 1, *convert))
+    };
+    shortest_path(from, version, false, adjacent, shortcuts)
+}
+
+/// Rejects more converters than there are steps between versions, which would otherwise be matched
+/// up with versions that do not exist.
+fn check_converters<C>(latest: u16, converters: &[C]) -> Result<()> {
+    let max = latest.saturating_sub(1);
+    if converters.len() > usize::from(max) {
+        return Err(Error::TooManyConverters {
+            latest,
+            got: converters.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Breadth-first search over the versions between `from` and `to`. Every edge moves towards `to`,
+/// so visiting versions in order finds the fewest steps.
+fn shortest_path<'c, S: Hop>(
+    from: u16,
+    to: u16,
+    upgrade: bool,
+    adjacent: impl Fn(u16) -> Option<(u16, S::Convert)>,
+    shortcuts: &[S],
+) -> Result<Path<'c, S::Convert>> {
+    let len = from.abs_diff(to) as usize + 1;
+    let version = |pos: usize| {
+        if upgrade {
+            from + pos as u16
+        } else {
+            from - pos as u16
+        }
+    };
+    let position = |v: u16| {
+        let pos = if upgrade {
+            v.checked_sub(from)
+        } else {
+            from.checked_sub(v)
+        };
+        pos.map(usize::from).filter(|&pos| pos < len)
+    };
+
+    // Fewest steps to reach each version, and the last step taken to get there
+    let mut steps = vec![usize::MAX; len];
+    let mut prev: Vec<Option<(usize, MigrationStep, S::Convert)>> = vec![None; len];
+    steps[0] = 0;
+
+    for pos in 0..len {
+        if steps[pos] == usize::MAX {
+            continue;
+        }
+
+        let v = version(pos);
+        let edges = adjacent(v).into_iter().chain(
+            shortcuts
+                .iter()
+                .map(Hop::hop)
+                .filter(|&(from, _, _)| from == v)
+                .map(|(_, to, convert)| (to, convert)),
+        );
+        for (next, convert) in edges {
+            let Some(next_pos) = position(next) else {
+                continue;
+            };
+            if steps[pos] + 1 < steps[next_pos] {
+                steps[next_pos] = steps[pos] + 1;
+                prev[next_pos] = Some((pos, MigrationStep { from: v, to: next }, convert));
+            }
+        }
+    }
+
+    if steps[len - 1] == usize::MAX {
+        return Err(Error::NoMigrationPath { from, to });
+    }
+
+    let mut path = Vec::with_capacity(steps[len - 1]);
+    let mut pos = len - 1;
+    while let Some((prev_pos, step, convert)) = prev[pos] {
+        path.push((step, convert));
+        pos = prev_pos;
+    }
+    path.reverse();
+
+    Ok(Path::Planned(path.into_iter()))
+}
@@ -0,0 +1,207 @@
+use core::{future::Future, time::Duration};
+
+use crate::{Error, MigrationStep, Result};
+
+/// Hooks called by `deserialize` and `serialize`, e.g. to count traffic per version or converter
+/// failures in a metrics system. The context, async and reporting variants call them too. Install
+/// one with `set_observer`.
+///
+/// `type_name` is the `core::any::type_name` of the versioned type. All methods do nothing by
+/// default.
+pub trait MigrationObserver: Send + Sync {
+    /// Called when `deserialize` is given a payload at `version`, before the version is checked.
+    fn on_deserialize(&self, type_name: &'static str, version: u16) {
+        let _ = (type_name, version);
+    }
+
+    /// Called when `serialize` is asked for `version`, before the version is checked.
+    fn on_serialize(&self, type_name: &'static str, version: u16) {
+        let _ = (type_name, version);
+    }
+
+    /// Called after a converter or shortcut succeeds.
+    fn on_step(&self, type_name: &'static str, step: MigrationStep, elapsed: Duration) {
+        let _ = (type_name, step, elapsed);
+    }
+
+    /// Called after a converter or shortcut fails.
+    fn on_step_error(&self, type_name: &'static str, step: MigrationStep, error: &anyhow::Error) {
+        let _ = (type_name, step, error);
+    }
+}
+
+#[cfg(feature = "std")]
+static OBSERVER: std::sync::OnceLock<&'static dyn MigrationObserver> = std::sync::OnceLock::new();
+
+/// Installs the observer for the whole process. Fails with `Error::ObserverAlreadySet` if one is
+/// already installed.
+#[cfg(feature = "std")]
+pub fn set_observer(observer: &'static dyn MigrationObserver) -> Result<()> {
+    OBSERVER
+        .set(observer)
+        .map_err(|_| Error::ObserverAlreadySet)
+}
+
+#[cfg(feature = "std")]
+fn observer() -> Option<&'static dyn MigrationObserver> {
+    OBSERVER.get().copied()
+}
+
+/// Keeps the `tracing` span for a `deserialize` or `serialize` call entered until dropped.
+pub(crate) struct Span {
+    #[cfg(feature = "tracing")]
+    _span: tracing::span::EnteredSpan,
+}
+
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn deserialize(type_name: &'static str, version: u16, latest: u16) -> Span {
+    #[cfg(feature = "std")]
+    if let Some(observer) = observer() {
+        observer.on_deserialize(type_name, version);
+    }
+
+    Span {
+        #[cfg(feature = "tracing")]
+        _span: tracing::debug_span!("vbare::deserialize", type_name, from = version, to = latest)
+            .entered(),
+    }
+}
+
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn serialize(type_name: &'static str, version: u16, latest: u16) -> Span {
+    #[cfg(feature = "std")]
+    if let Some(observer) = observer() {
+        observer.on_serialize(type_name, version);
+    }
+
+    Span {
+        #[cfg(feature = "tracing")]
+        _span: tracing::debug_span!("vbare::serialize", type_name, from = latest, to = version)
+            .entered(),
+    }
+}
+
+/// Like `deserialize`, for async calls. The span is entered each time `fut` is polled, since an
+/// entered span cannot be held across an await.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn deserialize_async<F: Future>(
+    type_name: &'static str,
+    version: u16,
+    latest: u16,
+    fut: F,
+) -> impl Future<Output = F::Output> {
+    #[cfg(feature = "std")]
+    if let Some(observer) = observer() {
+        observer.on_deserialize(type_name, version);
+    }
+
+    #[cfg(feature = "tracing")]
+    let fut = tracing::Instrument::instrument(
+        fut,
+        tracing::debug_span!("vbare::deserialize", type_name, from = version, to = latest),
+    );
+    fut
+}
+
+/// Like `serialize`, for async calls. See `deserialize_async`.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn serialize_async<F: Future>(
+    type_name: &'static str,
+    version: u16,
+    latest: u16,
+    fut: F,
+) -> impl Future<Output = F::Output> {
+    #[cfg(feature = "std")]
+    if let Some(observer) = observer() {
+        observer.on_serialize(type_name, version);
+    }
+
+    #[cfg(feature = "tracing")]
+    let fut = tracing::Instrument::instrument(
+        fut,
+        tracing::debug_span!("vbare::serialize", type_name, from = latest, to = version),
+    );
+    fut
+}
+
+/// Runs one converter or shortcut, reporting it to the observer and `tracing`.
+#[cfg_attr(
+    not(any(feature = "std", feature = "tracing")),
+    allow(unused_variables)
+)]
+pub(crate) fn step<T>(
+    type_name: &'static str,
+    step: MigrationStep,
+    data: T,
+    convert: impl FnOnce(T) -> anyhow::Result<T>,
+) -> Result<T> {
+    let MigrationStep { from, to } = step;
+
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("vbare::convert", type_name, from, to).entered();
+
+    #[cfg(feature = "std")]
+    if let Some(observer) = observer() {
+        let start = std::time::Instant::now();
+        return finish(observer, type_name, step, start, convert(data));
+    }
+
+    convert(data).map_err(|source| Error::Convert { from, to, source })
+}
+
+/// Like `step`, awaiting the converter's future.
+#[cfg_attr(
+    not(any(feature = "std", feature = "tracing")),
+    allow(unused_variables)
+)]
+pub(crate) fn step_async<T>(
+    type_name: &'static str,
+    step: MigrationStep,
+    convert: impl Future<Output = anyhow::Result<T>>,
+) -> impl Future<Output = Result<T>> {
+    let MigrationStep { from, to } = step;
+
+    let fut = async move {
+        #[cfg(feature = "std")]
+        if let Some(observer) = observer() {
+            let start = std::time::Instant::now();
+            return finish(observer, type_name, step, start, convert.await);
+        }
+
+        convert
+            .await
+            .map_err(|source| Error::Convert { from, to, source })
+    };
+
+    #[cfg(feature = "tracing")]
+    let fut = tracing::Instrument::instrument(
+        fut,
+        tracing::debug_span!("vbare::convert", type_name, from, to),
+    );
+    fut
+}
+
+/// Reports the outcome of a step that started at `start`.
+#[cfg(feature = "std")]
+fn finish<T>(
+    observer: &'static dyn MigrationObserver,
+    type_name: &'static str,
+    step: MigrationStep,
+    start: std::time::Instant,
+    result: anyhow::Result<T>,
+) -> Result<T> {
+    match result {
+        Ok(data) => {
+            observer.on_step(type_name, step, start.elapsed());
+            Ok(data)
+        }
+        Err(source) => {
+            observer.on_step_error(type_name, step, &source);
+            Err(Error::Convert {
+                from: step.from,
+                to: step.to,
+                source,
+            })
+        }
+    }
+}
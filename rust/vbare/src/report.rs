@@ -0,0 +1,156 @@
+use alloc::{borrow::Cow, vec::Vec};
+use core::fmt;
+
+use crate::{context, ContextOwnedVersionedData, Error, MigrationStep, Result};
+
+/// Data a downgrade could not represent in the older version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loss {
+    /// Version the converter that recorded the loss converted from.
+    pub from: u16,
+    /// Version the converter converted to.
+    pub to: u16,
+    /// Path of the field that was dropped or changed, e.g. `todos[].priority`.
+    pub path: Cow<'static, str>,
+    pub reason: Cow<'static, str>,
+}
+
+impl fmt::Display for Loss {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "downgrading version {} to version {} loses {}: {}",
+            self.from, self.to, self.path, self.reason
+        )
+    }
+}
+
+/// Losses recorded by serialize context converters while serializing.
+///
+/// Keep one in the context of a type, reachable through `AsMut<DowngradeReport>`, and record what
+/// each serialize converter drops. `ReportingOwnedVersionedData` attributes each loss to the step
+/// that recorded it and hands back the losses of each call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DowngradeReport {
+    losses: Vec<Loss>,
+    /// Step the running converter performs.
+    step: MigrationStep,
+}
+
+impl DowngradeReport {
+    /// Creates an empty report. Losses recorded outside `ReportingOwnedVersionedData` are
+    /// attributed to version 0.
+    pub fn new() -> Self {
+        Self {
+            losses: Vec::new(),
+            step: MigrationStep { from: 0, to: 0 },
+        }
+    }
+
+    /// Records that the current downgrade step drops or changes the field at `path`.
+    pub fn record(
+        &mut self,
+        path: impl Into<Cow<'static, str>>,
+        reason: impl Into<Cow<'static, str>>,
+    ) {
+        self.losses.push(Loss {
+            from: self.step.from,
+            to: self.step.to,
+            path: path.into(),
+            reason: reason.into(),
+        });
+    }
+
+    pub fn losses(&self) -> &[Loss] {
+        &self.losses
+    }
+
+    pub fn into_losses(self) -> Vec<Loss> {
+        self.losses
+    }
+
+    /// Returns true if no data was lost.
+    pub fn is_lossless(&self) -> bool {
+        self.losses.is_empty()
+    }
+}
+
+impl Default for DowngradeReport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AsMut<DowngradeReport> for DowngradeReport {
+    fn as_mut(&mut self) -> &mut DowngradeReport {
+        self
+    }
+}
+
+/// Serializing that reports the data older versions cannot represent.
+///
+/// Implemented for every `ContextOwnedVersionedData<Ctx>` whose context holds a `DowngradeReport`,
+/// so the serialize context converters and shortcuts that do the downgrade record what they drop
+/// in `ctx.as_mut()`. Use `DowngradeReport` itself as the context if the converters need nothing
+/// else.
+pub trait ReportingOwnedVersionedData<Ctx: AsMut<DowngradeReport> + ?Sized + 'static>:
+    ContextOwnedVersionedData<Ctx>
+{
+    /// Serializes like `ContextOwnedVersionedData::serialize_with_context` and returns what was
+    /// lost on the way. The losses are taken out of the report in `ctx`.
+    fn serialize_with_report(
+        self,
+        version: u16,
+        ctx: &mut Ctx,
+    ) -> Result<(Vec<u8>, DowngradeReport)> {
+        downgrade(self, version, ctx, false)
+    }
+
+    /// Serializes like `ContextOwnedVersionedData::serialize_with_context`, failing with
+    /// `Error::LossyDowngrade` for the first loss a step records.
+    fn serialize_strict(self, version: u16, ctx: &mut Ctx) -> Result<Vec<u8>> {
+        downgrade(self, version, ctx, true).map(|(payload, _)| payload)
+    }
+}
+
+impl<Ctx, T> ReportingOwnedVersionedData<Ctx> for T
+where
+    Ctx: AsMut<DowngradeReport> + ?Sized + 'static,
+    T: ContextOwnedVersionedData<Ctx>,
+{
+}
+
+fn downgrade<Ctx, T>(
+    data: T,
+    version: u16,
+    ctx: &mut Ctx,
+    strict: bool,
+) -> Result<(Vec<u8>, DowngradeReport)>
+where
+    Ctx: AsMut<DowngradeReport> + ?Sized + 'static,
+    T: ReportingOwnedVersionedData<Ctx>,
+{
+    // Losses already in the report belong to the caller
+    let start = ctx.as_mut().losses.len();
+    let result = context::serialize(data, version, ctx, |data, step, convert, ctx| {
+        let before = ctx.as_mut().losses.len();
+        ctx.as_mut().step = step;
+        let data = context::run_step(data, step, convert, ctx)?;
+
+        if strict {
+            if let Some(loss) = ctx.as_mut().losses.drain(before..).next() {
+                return Err(Error::LossyDowngrade(loss));
+            }
+        }
+        Ok(data)
+    });
+
+    let losses = ctx.as_mut().losses.split_off(start);
+    Ok((
+        result?,
+        DowngradeReport {
+            losses,
+            ..DowngradeReport::new()
+        },
+    ))
+}
@@ -0,0 +1,128 @@
+//! Version negotiation for connections where both peers agree on a single version up front.
+//!
+//! Each peer sends a handshake holding the range of versions it supports, then reads the other
+//! peer's handshake. Both sides pick the highest version in both ranges, so no further round trip
+//! is needed:
+//!
+//! ```text
+//! +-------------------+-------------------+
+//! |    Min Version    |    Max Version    |
+//! |   (uint16, 2B)    |   (uint16, 2B)    |
+//! +-------------------+-------------------+
+//! ```
+//!
+//! Messages exchanged after the handshake do not carry a version.
+
+use alloc::vec::Vec;
+use core::{fmt, marker::PhantomData};
+
+pub use crate::VersionRange;
+use crate::{Error, OwnedVersionedData, Result};
+
+/// Size of an encoded handshake in bytes.
+pub const HANDSHAKE_LEN: usize = 4;
+
+/// A connection that receives `In` and sends `Out` at a negotiated version.
+pub struct VersionedSession<In, Out> {
+    version: u16,
+    _marker: PhantomData<fn(In) -> Out>,
+}
+
+impl<In: OwnedVersionedData, Out: OwnedVersionedData> VersionedSession<In, Out> {
+    /// Versions both `In` and `Out` can be decoded and encoded at.
+    ///
+    /// Returns `Error::InvalidVersionRange` if their supported versions do not overlap.
+    pub fn supported_versions() -> Result<VersionRange> {
+        let (inbound, outbound) = (In::supported_versions(), Out::supported_versions());
+        VersionRange::new(inbound.min.max(outbound.min), inbound.max.min(outbound.max))
+    }
+
+    /// Creates a session at a version that was agreed on out of band.
+    pub fn new(version: u16) -> Result<Self> {
+        let supported = Self::supported_versions()?;
+        if !supported.contains(version) {
+            return Err(Error::NoCommonVersion {
+                local: supported,
+                remote: VersionRange {
+                    min: version,
+                    max: version,
+                },
+            });
+        }
+
+        Ok(Self {
+            version,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Creates a session at the highest version supported by both this side and `remote`.
+    pub fn negotiate(remote: &VersionRange) -> Result<Self> {
+        let version = Self::supported_versions()?.negotiate(remote)?;
+        Ok(Self {
+            version,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Exchanges handshakes over `stream` and negotiates the session version.
+    #[cfg(feature = "std")]
+    pub fn handshake<S: std::io::Read + std::io::Write>(mut stream: S) -> Result<Self> {
+        stream.write_all(&Self::supported_versions()?.to_bytes())?;
+        stream.flush()?;
+
+        let mut remote = [0u8; HANDSHAKE_LEN];
+        stream.read_exact(&mut remote)?;
+        Self::negotiate(&VersionRange::from_bytes(remote)?)
+    }
+
+    /// Exchanges handshakes over `stream` and negotiates the session version.
+    #[cfg(feature = "tokio")]
+    pub async fn handshake_async<S>(mut stream: S) -> Result<Self>
+    where
+        S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+    {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        stream
+            .write_all(&Self::supported_versions()?.to_bytes())
+            .await?;
+        stream.flush().await?;
+
+        let mut remote = [0u8; HANDSHAKE_LEN];
+        stream.read_exact(&mut remote).await?;
+        Self::negotiate(&VersionRange::from_bytes(remote)?)
+    }
+
+    /// Negotiated version.
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// Deserializes an inbound message encoded at the negotiated version.
+    pub fn decode(&self, payload: &[u8]) -> Result<In::Latest> {
+        In::deserialize(payload, self.version)
+    }
+
+    /// Serializes an outbound message at the negotiated version.
+    pub fn encode(&self, message: Out::Latest) -> Result<Vec<u8>> {
+        Out::wrap_latest(message).serialize(self.version)
+    }
+}
+
+impl<In, Out> Clone for VersionedSession<In, Out> {
+    fn clone(&self) -> Self {
+        Self {
+            version: self.version,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<In, Out> fmt::Debug for VersionedSession<In, Out> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VersionedSession")
+            .field("version", &self.version)
+            .finish()
+    }
+}
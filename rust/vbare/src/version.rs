@@ -0,0 +1,72 @@
+use core::fmt;
+
+use crate::session::HANDSHAKE_LEN;
+use crate::{Error, Result};
+
+/// An inclusive range of versions.
+///
+/// This is also the handshake message exchanged by `session`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionRange {
+    pub(crate) min: u16,
+    pub(crate) max: u16,
+}
+
+impl VersionRange {
+    /// Returns `Error::InvalidVersionRange` if `min` is 0 or greater than `max`.
+    pub fn new(min: u16, max: u16) -> Result<Self> {
+        if min == 0 || min > max {
+            return Err(Error::InvalidVersionRange { min, max });
+        }
+
+        Ok(Self { min, max })
+    }
+
+    pub fn min(&self) -> u16 {
+        self.min
+    }
+
+    pub fn max(&self) -> u16 {
+        self.max
+    }
+
+    pub fn contains(&self, version: u16) -> bool {
+        self.min <= version && version <= self.max
+    }
+
+    /// Returns the versions in both ranges, or `None` if they do not overlap.
+    pub fn intersect(&self, other: &VersionRange) -> Option<VersionRange> {
+        let min = self.min.max(other.min);
+        let max = self.max.min(other.max);
+        (min <= max).then_some(VersionRange { min, max })
+    }
+
+    /// Returns the highest version in both ranges.
+    pub fn negotiate(&self, remote: &VersionRange) -> Result<u16> {
+        self.intersect(remote)
+            .map(|common| common.max)
+            .ok_or(Error::NoCommonVersion {
+                local: *self,
+                remote: *remote,
+            })
+    }
+
+    pub fn to_bytes(&self) -> [u8; HANDSHAKE_LEN] {
+        let [min0, min1] = self.min.to_le_bytes();
+        let [max0, max1] = self.max.to_le_bytes();
+        [min0, min1, max0, max1]
+    }
+
+    pub fn from_bytes(bytes: [u8; HANDSHAKE_LEN]) -> Result<Self> {
+        Self::new(
+            u16::from_le_bytes([bytes[0], bytes[1]]),
+            u16::from_le_bytes([bytes[2], bytes[3]]),
+        )
+    }
+}
+
+impl fmt::Display for VersionRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..={}", self.min, self.max)
+    }
+}
@@ -0,0 +1,237 @@
+use anyhow::*;
+use serde::{Deserialize, Serialize};
+use vbare::{AsyncConverter, AsyncOwnedVersionedData, OwnedVersionedData};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct UserV1 {
+    id: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct UserV2 {
+    id: u32,
+    name: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum User {
+    V1(UserV1),
+    V2(UserV2),
+}
+
+/// In-memory stand-in for a database that v1 -> v2 looks names up in.
+struct Directory {
+    rows: Vec<(u32, String)>,
+}
+
+impl Directory {
+    fn new() -> Self {
+        Directory {
+            rows: vec![(1, "ada".to_string()), (2, "grace".to_string())],
+        }
+    }
+
+    async fn name(&self, id: u32) -> Result<String> {
+        tokio::task::yield_now().await;
+        self.rows
+            .iter()
+            .find(|(row_id, _)| *row_id == id)
+            .map(|(_, name)| name.clone())
+            .ok_or_else(|| anyhow!("user {id} not found"))
+    }
+}
+
+impl OwnedVersionedData for User {
+    type Latest = UserV2;
+
+    const LATEST_VERSION: u16 = 2;
+
+    fn wrap_latest(latest: UserV2) -> Self {
+        User::V2(latest)
+    }
+
+    fn unwrap_latest(self) -> Result<Self::Latest> {
+        match self {
+            User::V2(data) => Ok(data),
+            _ => bail!("version not latest"),
+        }
+    }
+
+    fn deserialize_version(payload: &[u8], version: u16) -> Result<Self> {
+        match version {
+            1 => Ok(User::V1(serde_bare::from_slice(payload)?)),
+            2 => Ok(User::V2(serde_bare::from_slice(payload)?)),
+            _ => bail!("invalid version: {version}"),
+        }
+    }
+
+    fn serialize_version(self, _version: u16) -> Result<Vec<u8>> {
+        match self {
+            User::V1(data) => serde_bare::to_vec(&data).map_err(Into::into),
+            User::V2(data) => serde_bare::to_vec(&data).map_err(Into::into),
+        }
+    }
+}
+
+impl AsyncOwnedVersionedData<Directory> for User {
+    fn deserialize_async_converters() -> &'static [AsyncConverter<Self, Directory>] {
+        &[|user, directory| {
+            Box::pin(async move {
+                match user {
+                    User::V1(v1) => Ok(User::V2(UserV2 {
+                        id: v1.id,
+                        name: directory.name(v1.id).await?,
+                    })),
+                    other => Ok(other),
+                }
+            })
+        }]
+    }
+
+    fn serialize_async_converters() -> &'static [AsyncConverter<Self, Directory>] {
+        &[|user, _| {
+            Box::pin(async move {
+                match user {
+                    User::V2(v2) => Ok(User::V1(UserV1 { id: v2.id })),
+                    other => Ok(other),
+                }
+            })
+        }]
+    }
+}
+
+/// `User` with no async converters, so only the latest version can be read.
+#[derive(Debug, Clone, PartialEq)]
+struct Latest(User);
+
+impl OwnedVersionedData for Latest {
+    type Latest = UserV2;
+
+    const LATEST_VERSION: u16 = 2;
+
+    fn wrap_latest(latest: UserV2) -> Self {
+        Latest(User::wrap_latest(latest))
+    }
+
+    fn unwrap_latest(self) -> Result<Self::Latest> {
+        self.0.unwrap_latest()
+    }
+
+    fn deserialize_version(payload: &[u8], version: u16) -> Result<Self> {
+        User::deserialize_version(payload, version).map(Latest)
+    }
+
+    fn serialize_version(self, version: u16) -> Result<Vec<u8>> {
+        self.0.serialize_version(version)
+    }
+}
+
+impl AsyncOwnedVersionedData<()> for Latest {}
+
+#[tokio::test]
+async fn test_async_upgrade_fetches_data() {
+    let directory = Directory::new();
+    let payload = serde_bare::to_vec(&UserV1 { id: 2 }).unwrap();
+    assert_eq!(
+        User::deserialize_async(&payload, 1, &directory)
+            .await
+            .unwrap(),
+        UserV2 {
+            id: 2,
+            name: "grace".to_string(),
+        }
+    );
+
+    let embedded = [&1u16.to_le_bytes()[..], &payload].concat();
+    assert_eq!(
+        User::deserialize_with_embedded_version_async(&embedded, &directory)
+            .await
+            .unwrap()
+            .name,
+        "grace"
+    );
+}
+
+#[tokio::test]
+async fn test_async_downgrade() {
+    let directory = Directory::new();
+    let user = UserV2 {
+        id: 1,
+        name: "ada".to_string(),
+    };
+
+    let payload = User::wrap_latest(user.clone())
+        .serialize_async(1, &directory)
+        .await
+        .unwrap();
+    assert_eq!(
+        serde_bare::from_slice::<UserV1>(&payload).unwrap(),
+        UserV1 { id: 1 }
+    );
+
+    let embedded = User::wrap_latest(user.clone())
+        .serialize_with_embedded_version_async(2, &directory)
+        .await
+        .unwrap();
+    assert_eq!(
+        User::deserialize_with_embedded_version_async(&embedded, &directory)
+            .await
+            .unwrap(),
+        user
+    );
+}
+
+#[tokio::test]
+async fn test_async_lookup_error() {
+    let directory = Directory::new();
+    let payload = serde_bare::to_vec(&UserV1 { id: 3 }).unwrap();
+    let err = User::deserialize_async(&payload, 1, &directory)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, vbare::Error::Convert { from: 1, to: 2, .. }));
+    assert_eq!(
+        core::error::Error::source(&err).unwrap().to_string(),
+        "user 3 not found"
+    );
+
+    assert!(matches!(
+        User::deserialize_async(&payload, 3, &directory).await,
+        Err(vbare::Error::UnsupportedVersion { got: 3, latest: 2 })
+    ));
+}
+
+#[tokio::test]
+async fn test_async_missing_converters() {
+    let payload = serde_bare::to_vec(&UserV1 { id: 1 }).unwrap();
+    assert!(matches!(
+        Latest::deserialize_async(&payload, 1, &()).await,
+        Err(vbare::Error::NoMigrationPath { from: 1, to: 2 })
+    ));
+
+    let payload = serde_bare::to_vec(&UserV2 {
+        id: 1,
+        name: "ada".to_string(),
+    })
+    .unwrap();
+    assert_eq!(
+        Latest::deserialize_async(&payload, 2, &())
+            .await
+            .unwrap()
+            .name,
+        "ada"
+    );
+}
+
+#[test]
+fn test_async_futures_are_send() {
+    fn assert_send<T: Send>(_: T) {}
+    let directory = Directory::new();
+    assert_send(User::deserialize_async(&[], 1, &directory));
+    assert_send(
+        User::wrap_latest(UserV2 {
+            id: 1,
+            name: String::new(),
+        })
+        .serialize_async(1, &directory),
+    );
+}
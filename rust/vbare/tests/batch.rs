@@ -0,0 +1,61 @@
+mod common;
+
+use common::{message, table, Message, MessageV1};
+use vbare::batch::{self, Record};
+use vbare::OwnedVersionedData;
+
+#[test]
+fn test_migrate_iterator() {
+    let table = table();
+    let records: Vec<_> = batch::migrate::<Message, _>(&table, 2).collect();
+    assert_eq!(records.len(), 4);
+
+    let Ok(Record::Migrated(migrated)) = &records[0] else {
+        panic!("expected migrated record, got {:?}", records[0]);
+    };
+    assert_eq!(
+        Message::deserialize_with_embedded_version(migrated).unwrap(),
+        message(1, "")
+    );
+
+    // Current records are passed through as is
+    assert!(matches!(&records[1], Ok(Record::Current(payload)) if *payload == &table[1]));
+
+    assert!(matches!(records[2], Err(vbare::Error::TruncatedHeader)));
+    assert!(matches!(
+        records[3],
+        Err(vbare::Error::UnsupportedVersion { got: 3, latest: 2 })
+    ));
+}
+
+#[test]
+fn test_migrate_summary() {
+    let summary = batch::migrate::<Message, _>(table(), 1).summarize();
+
+    assert_eq!(summary.current, 1);
+    assert_eq!(summary.migrated.len(), 1);
+    let (index, migrated) = &summary.migrated[0];
+    assert_eq!(*index, 1);
+    assert_eq!(
+        migrated[..],
+        [
+            &1u16.to_le_bytes()[..],
+            &serde_bare::to_vec(&MessageV1 { id: 2 }).unwrap()
+        ]
+        .concat()
+    );
+
+    let errors: Vec<_> = summary.errors.iter().map(|(index, _)| *index).collect();
+    assert_eq!(errors, vec![2, 3]);
+}
+
+#[test]
+fn test_migrate_unsupported_target() {
+    let summary = batch::migrate::<Message, _>(&table()[..2], 3).summarize();
+    assert_eq!(summary.current, 0);
+    assert!(summary.migrated.is_empty());
+    assert!(summary
+        .errors
+        .iter()
+        .all(|(_, err)| matches!(err, vbare::Error::UnsupportedVersion { got: 3, .. })));
+}
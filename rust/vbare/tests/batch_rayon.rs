@@ -0,0 +1,38 @@
+mod common;
+
+use common::{message, table, Message};
+use vbare::batch;
+use vbare::OwnedVersionedData;
+
+#[test]
+fn test_par_migrate() {
+    let summary = batch::par_migrate::<Message, _>(&table(), 2);
+    let sequential = batch::migrate::<Message, _>(table(), 2).summarize();
+    assert_eq!(summary.migrated, sequential.migrated);
+    assert_eq!(summary.current, sequential.current);
+    assert_eq!(summary.errors.len(), sequential.errors.len());
+
+    let (index, migrated) = &summary.migrated[0];
+    assert_eq!(*index, 0);
+    assert_eq!(
+        Message::deserialize_with_embedded_version(migrated).unwrap(),
+        message(1, "")
+    );
+}
+
+#[test]
+fn test_par_migrate_many() {
+    let table: Vec<_> = (0..1000)
+        .map(|id| {
+            Message::wrap_latest(message(id, "hi"))
+                .serialize_with_embedded_version(1 + (id % 2) as u16)
+                .unwrap()
+        })
+        .collect();
+
+    let summary = batch::par_migrate::<Message, _>(&table, 2);
+    assert_eq!(summary.current, 500);
+    assert!(summary.errors.is_empty());
+    let indexes: Vec<_> = summary.migrated.iter().map(|(index, _)| *index).collect();
+    assert_eq!(indexes, (0..1000).step_by(2).collect::<Vec<_>>());
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use bytes::BytesMut;
+use common::{message, Message};
+use futures::{SinkExt, StreamExt};
+use tokio_util::codec::{Decoder, Encoder, Framed};
+use vbare::{codec::VbareCodec, framing::FrameEncoder, OwnedVersionedData};
+
+#[tokio::test]
+async fn test_framed_roundtrip() {
+    let (client, server) = tokio::io::duplex(64);
+    let mut client = Framed::new(client, VbareCodec::<Message>::new(1));
+    let mut server = Framed::new(server, VbareCodec::<Message>::new(2));
+
+    // Client speaks v1, so the body is dropped on the way out
+    client.send(message(1, "dropped")).await.unwrap();
+    assert_eq!(server.next().await.unwrap().unwrap(), message(1, ""));
+
+    server.send(message(2, "kept")).await.unwrap();
+    assert_eq!(client.next().await.unwrap().unwrap(), message(2, "kept"));
+
+    // Switch the outbound version mid-stream
+    client.codec_mut().set_version(2);
+    client.send(message(3, "kept")).await.unwrap();
+    assert_eq!(server.next().await.unwrap().unwrap(), message(3, "kept"));
+
+    drop(client);
+    assert!(server.next().await.is_none());
+}
+
+#[test]
+fn test_matches_frame_encoder() {
+    let mut buf = BytesMut::new();
+    VbareCodec::<Message>::new(2)
+        .encode(message(7, "hi"), &mut buf)
+        .unwrap();
+
+    let mut expected = Vec::new();
+    FrameEncoder::<Message>::new()
+        .encode(Message::wrap_latest(message(7, "hi")), 2, &mut expected)
+        .unwrap();
+    assert_eq!(&buf[..], &expected[..]);
+}
+
+#[test]
+fn test_decode_partial_and_bad_frames() {
+    let mut codec = VbareCodec::<Message>::new(2);
+    let mut encoded = Vec::new();
+    vbare::framing::write_frame(2, &[0xff], usize::MAX, &mut encoded).unwrap();
+    FrameEncoder::<Message>::new()
+        .encode(Message::wrap_latest(message(3, "ok")), 2, &mut encoded)
+        .unwrap();
+
+    let (bad, good) = encoded.split_at(7);
+    let mut buf = BytesMut::from(bad);
+    assert!(matches!(
+        codec.decode(&mut buf),
+        Err(vbare::Error::Decode { version: 2, .. })
+    ));
+    assert!(buf.is_empty());
+
+    buf.extend_from_slice(&good[..good.len() - 1]);
+    assert_eq!(codec.decode(&mut buf).unwrap(), None);
+    buf.extend_from_slice(&good[good.len() - 1..]);
+    assert_eq!(codec.decode(&mut buf).unwrap(), Some(message(3, "ok")));
+}
+
+#[test]
+fn test_skip_frame_shorter_than_version() {
+    let mut codec = VbareCodec::<Message>::new(2);
+    let mut encoded = vec![1, 0, 0, 0, 2];
+    FrameEncoder::<Message>::new()
+        .encode(Message::wrap_latest(message(3, "ok")), 2, &mut encoded)
+        .unwrap();
+
+    let mut buf = BytesMut::from(&encoded[..]);
+    assert!(matches!(
+        codec.decode(&mut buf),
+        Err(vbare::Error::TruncatedHeader)
+    ));
+    assert_eq!(codec.decode(&mut buf).unwrap(), Some(message(3, "ok")));
+}
+
+#[test]
+fn test_frame_too_large() {
+    let mut codec = VbareCodec::<Message>::new(2).with_max_frame_length(4);
+    let mut buf = BytesMut::new();
+    assert!(matches!(
+        codec.encode(message(1, "too long"), &mut buf),
+        Err(vbare::Error::FrameTooLarge { max: 4, .. })
+    ));
+    assert!(buf.is_empty());
+
+    buf.extend_from_slice(&16u32.to_le_bytes());
+    assert!(matches!(
+        codec.decode(&mut buf),
+        Err(vbare::Error::FrameTooLarge { len: 16, max: 4 })
+    ));
+}
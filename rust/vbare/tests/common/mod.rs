@@ -0,0 +1,105 @@
+#![allow(dead_code)]
+
+use anyhow::*;
+use serde::{Deserialize, Serialize};
+use vbare::{Converter, OwnedVersionedData};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageV1 {
+    pub id: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageV2 {
+    pub id: u32,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    V1(MessageV1),
+    V2(MessageV2),
+}
+
+impl OwnedVersionedData for Message {
+    type Latest = MessageV2;
+
+    const LATEST_VERSION: u16 = 2;
+
+    fn wrap_latest(latest: MessageV2) -> Self {
+        Message::V2(latest)
+    }
+
+    fn unwrap_latest(self) -> Result<Self::Latest> {
+        match self {
+            Message::V2(data) => Ok(data),
+            _ => bail!("version not latest"),
+        }
+    }
+
+    fn deserialize_version(payload: &[u8], version: u16) -> Result<Self> {
+        match version {
+            1 => Ok(Message::V1(serde_bare::from_slice(payload)?)),
+            2 => Ok(Message::V2(serde_bare::from_slice(payload)?)),
+            _ => bail!("invalid version: {version}"),
+        }
+    }
+
+    fn serialize_version(self, _version: u16) -> Result<Vec<u8>> {
+        match self {
+            Message::V1(data) => serde_bare::to_vec(&data).map_err(Into::into),
+            Message::V2(data) => serde_bare::to_vec(&data).map_err(Into::into),
+        }
+    }
+
+    fn deserialize_converters() -> &'static [Converter<Self>] {
+        &[Self::v1_to_v2]
+    }
+
+    fn serialize_converters() -> &'static [Converter<Self>] {
+        &[Self::v2_to_v1]
+    }
+}
+
+impl Message {
+    fn v1_to_v2(self) -> Result<Self> {
+        match self {
+            Message::V1(v1) => Ok(Message::V2(MessageV2 {
+                id: v1.id,
+                body: String::new(),
+            })),
+            other => Ok(other),
+        }
+    }
+
+    fn v2_to_v1(self) -> Result<Self> {
+        match self {
+            Message::V2(v2) => Ok(Message::V1(MessageV1 { id: v2.id })),
+            other => Ok(other),
+        }
+    }
+}
+
+pub fn message(id: u32, body: &str) -> MessageV2 {
+    MessageV2 {
+        id,
+        body: body.to_string(),
+    }
+}
+
+/// Stored records as written by clients at different versions: a v1 record, a v2 record, a
+/// truncated header and a record from a newer version.
+pub fn table() -> Vec<Vec<u8>> {
+    vec![
+        [
+            &1u16.to_le_bytes()[..],
+            &serde_bare::to_vec(&MessageV1 { id: 1 }).unwrap(),
+        ]
+        .concat(),
+        Message::wrap_latest(message(2, "hi"))
+            .serialize_with_embedded_version(2)
+            .unwrap(),
+        vec![2],
+        3u16.to_le_bytes().to_vec(),
+    ]
+}
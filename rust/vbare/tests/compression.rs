@@ -0,0 +1,139 @@
+mod common;
+
+use std::borrow::Cow;
+
+use common::{message, Message};
+use vbare::envelope::{Checksummed, Codec, Compressed, Embedded};
+use vbare::{Envelope, OwnedVersionedData};
+
+const ZSTD: Codec = Codec::Zstd { level: 3 };
+
+fn large() -> Vec<u8> {
+    b"compressible ".repeat(100)
+}
+
+#[test]
+fn test_compress_above_threshold() {
+    for codec in [ZSTD, Codec::Lz4] {
+        let envelope = Compressed::new(Embedded, codec);
+
+        let sealed = envelope.seal(2, &large()).unwrap();
+        assert_eq!(sealed[..2], [2, 0]);
+        assert_ne!(sealed[2], 0);
+        assert!(sealed.len() < large().len() / 4);
+
+        let (version, payload) = envelope.open(&sealed).unwrap();
+        assert_eq!(version, 2);
+        assert!(matches!(payload, Cow::Owned(_)));
+        assert_eq!(payload, large());
+    }
+}
+
+#[test]
+fn test_store_below_threshold() {
+    let envelope = Compressed::new(Embedded, ZSTD);
+    assert_eq!(envelope.threshold(), 256);
+
+    let sealed = envelope.seal(1, b"small").unwrap();
+    assert_eq!(sealed, [&[1, 0, 0][..], b"small"].concat());
+    let (_, payload) = envelope.open(&sealed).unwrap();
+    assert!(matches!(payload, Cow::Borrowed(b"small")));
+
+    // Payloads that don't shrink are stored as is
+    let incompressible: Vec<u8> = (0..=255).collect();
+    let sealed = envelope.with_threshold(0).seal(1, &incompressible).unwrap();
+    assert_eq!(sealed[2], 0);
+    assert_eq!(sealed[3..], incompressible);
+}
+
+#[test]
+fn test_open_any_codec() {
+    let sealed = Compressed::new(Embedded, Codec::Lz4)
+        .seal(1, &large())
+        .unwrap();
+    let (_, payload) = Compressed::new(Embedded, ZSTD).open(&sealed).unwrap();
+    assert_eq!(payload, large());
+
+    assert!(matches!(
+        Compressed::new(Embedded, ZSTD).open(&[1, 0, 9, 1, 2, 3]),
+        Err(vbare::Error::UnknownCodec { codec: 9 })
+    ));
+    assert!(matches!(
+        Compressed::new(Embedded, ZSTD).open(&[1, 0, 1, 1, 2, 3]),
+        Err(vbare::Error::Compression { .. })
+    ));
+    assert!(matches!(
+        Compressed::new(Embedded, ZSTD).open(&[1, 0]),
+        Err(vbare::Error::TruncatedHeader)
+    ));
+}
+
+#[test]
+fn test_compressed_messages() {
+    let latest = message(1, &"hello ".repeat(100));
+    let envelope = Checksummed(Compressed::new(Embedded, ZSTD).with_threshold(64));
+
+    for version in [1, 2] {
+        let sealed = Message::wrap_latest(latest.clone())
+            .serialize_with_envelope(version, envelope)
+            .unwrap();
+        let expected = if version == 2 {
+            latest.clone()
+        } else {
+            message(1, "")
+        };
+        assert_eq!(
+            Message::deserialize_with_envelope(&sealed, envelope).unwrap(),
+            expected
+        );
+    }
+}
+
+#[test]
+fn test_reject_oversized_payload() {
+    // An lz4 size prefix claiming 4 GiB is rejected before anything is allocated
+    let hostile = [1, 0, 2, 0xff, 0xff, 0xff, 0xff, 0];
+    let envelope = Compressed::new(Embedded, Codec::Lz4);
+    assert_eq!(envelope.max_len(), 16 * 1024 * 1024);
+    assert!(matches!(
+        envelope.open(&hostile),
+        Err(vbare::Error::DecompressedTooLarge {
+            len: 0xffff_ffff,
+            max: 16_777_216,
+        })
+    ));
+
+    for codec in [ZSTD, Codec::Lz4] {
+        let sealed = Compressed::new(Embedded, codec).seal(1, &large()).unwrap();
+
+        let envelope = Compressed::new(Embedded, codec).with_max_len(large().len() - 1);
+        assert!(matches!(
+            envelope.open(&sealed),
+            Err(vbare::Error::DecompressedTooLarge {
+                len: 1300,
+                max: 1299
+            })
+        ));
+
+        let envelope = envelope.with_max_len(large().len());
+        assert_eq!(envelope.open(&sealed).unwrap().1, large());
+    }
+
+    // Frames without a recorded size are decoded up to the limit
+    let frame = zstd::stream::encode_all(&large()[..], 3).unwrap();
+    let sealed = [&[1, 0, 1][..], &frame].concat();
+    let envelope = Compressed::new(Embedded, ZSTD);
+    assert_eq!(envelope.open(&sealed).unwrap().1, large());
+    assert!(matches!(
+        envelope.with_max_len(100).open(&sealed),
+        Err(vbare::Error::DecompressedTooLarge { len: 101, max: 100 })
+    ));
+    assert_eq!(
+        envelope
+            .with_max_len(large().len())
+            .open(&sealed)
+            .unwrap()
+            .1,
+        large()
+    );
+}
@@ -0,0 +1,308 @@
+use std::borrow::Cow;
+
+use anyhow::*;
+use serde::{Deserialize, Serialize};
+use vbare::{
+    ContextConverter, ContextOwnedVersionedData, ContextShortcut, ContextVersionedData,
+    OwnedVersionedData, VersionedData,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TodoV1 {
+    title: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TodoV2 {
+    id: u64,
+    title: String,
+    created_at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TodoV3 {
+    id: u64,
+    title: String,
+    created_at: u64,
+    updated_at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Todo {
+    V1(TodoV1),
+    V2(TodoV2),
+    V3(TodoV3),
+}
+
+/// Ambient state the migrations need.
+struct Migration {
+    now: u64,
+    next_id: u64,
+    steps: usize,
+    downgraded: Vec<u64>,
+}
+
+impl Migration {
+    fn v1_to_v2(&mut self, v1: TodoV1) -> TodoV2 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.steps += 1;
+        TodoV2 {
+            id,
+            title: v1.title,
+            created_at: self.now,
+        }
+    }
+
+    fn v2_to_v3(&mut self, v2: TodoV2) -> TodoV3 {
+        self.steps += 1;
+        TodoV3 {
+            id: v2.id,
+            title: v2.title,
+            created_at: v2.created_at,
+            updated_at: self.now,
+        }
+    }
+}
+
+impl OwnedVersionedData for Todo {
+    type Latest = TodoV3;
+
+    const LATEST_VERSION: u16 = 3;
+
+    fn wrap_latest(latest: TodoV3) -> Self {
+        Todo::V3(latest)
+    }
+
+    fn unwrap_latest(self) -> Result<Self::Latest> {
+        match self {
+            Todo::V3(data) => Ok(data),
+            _ => bail!("version not latest"),
+        }
+    }
+
+    fn deserialize_version(payload: &[u8], version: u16) -> Result<Self> {
+        match version {
+            1 => Ok(Todo::V1(serde_bare::from_slice(payload)?)),
+            2 => Ok(Todo::V2(serde_bare::from_slice(payload)?)),
+            3 => Ok(Todo::V3(serde_bare::from_slice(payload)?)),
+            _ => bail!("invalid version: {version}"),
+        }
+    }
+
+    fn serialize_version(self, _version: u16) -> Result<Vec<u8>> {
+        match self {
+            Todo::V1(data) => serde_bare::to_vec(&data).map_err(Into::into),
+            Todo::V2(data) => serde_bare::to_vec(&data).map_err(Into::into),
+            Todo::V3(data) => serde_bare::to_vec(&data).map_err(Into::into),
+        }
+    }
+}
+
+impl ContextOwnedVersionedData<Migration> for Todo {
+    fn deserialize_context_converters() -> &'static [ContextConverter<Self, Migration>] {
+        &[
+            |todo, ctx| match todo {
+                Todo::V1(v1) => Ok(Todo::V2(ctx.v1_to_v2(v1))),
+                other => Ok(other),
+            },
+            |todo, ctx| match todo {
+                Todo::V2(v2) => Ok(Todo::V3(ctx.v2_to_v3(v2))),
+                other => Ok(other),
+            },
+        ]
+    }
+
+    // Only the latest version can be written back
+    fn serialize_context_converters() -> &'static [ContextConverter<Self, Migration>] {
+        &[|todo, ctx| match todo {
+            Todo::V3(v3) => {
+                ctx.downgraded.push(v3.id);
+                Ok(Todo::V2(TodoV2 {
+                    id: v3.id,
+                    title: v3.title,
+                    created_at: v3.created_at,
+                }))
+            }
+            other => Ok(other),
+        }]
+    }
+
+    fn serialize_context_shortcuts() -> &'static [ContextShortcut<Self, Migration>] {
+        &[ContextShortcut {
+            from: 3,
+            to: 1,
+            convert: |todo, ctx| match todo {
+                Todo::V3(v3) => {
+                    ctx.downgraded.push(v3.id);
+                    Ok(Todo::V1(TodoV1 { title: v3.title }))
+                }
+                other => Ok(other),
+            },
+        }]
+    }
+}
+
+/// Borrowed two-version type whose upgrade prefixes names from a `str` context.
+enum Name<'a> {
+    V1(Cow<'a, str>),
+    V2(Cow<'a, str>),
+}
+
+impl<'a> VersionedData<'a> for Name<'a> {
+    type Latest = Cow<'a, str>;
+
+    const LATEST_VERSION: u16 = 2;
+
+    fn wrap_latest(latest: Cow<'a, str>) -> Self {
+        Name::V2(latest)
+    }
+
+    fn unwrap_latest(self) -> Result<Self::Latest> {
+        match self {
+            Name::V2(data) => Ok(data),
+            _ => bail!("version not latest"),
+        }
+    }
+
+    fn deserialize_version(payload: &'a [u8], version: u16) -> Result<Self> {
+        let name = Cow::Borrowed(std::str::from_utf8(payload)?);
+        match version {
+            1 => Ok(Name::V1(name)),
+            2 => Ok(Name::V2(name)),
+            _ => bail!("invalid version: {version}"),
+        }
+    }
+
+    fn serialize_version(self, _version: u16) -> Result<Vec<u8>> {
+        match self {
+            Name::V1(name) | Name::V2(name) => Ok(name.into_owned().into_bytes()),
+        }
+    }
+}
+
+impl<'a> ContextVersionedData<'a, str> for Name<'a> {
+    fn deserialize_context_converters() -> &'a [ContextConverter<Self, str>] {
+        &[|name, prefix| match name {
+            Name::V1(name) => Ok(Name::V2(Cow::Owned(format!("{prefix}{name}")))),
+            other => Ok(other),
+        }]
+    }
+
+    fn serialize_context_converters() -> &'a [ContextConverter<Self, str>] {
+        &[|name, prefix| match name {
+            Name::V2(name) => Ok(Name::V1(Cow::Owned(
+                name.strip_prefix(&*prefix).unwrap_or(&name).to_string(),
+            ))),
+            other => Ok(other),
+        }]
+    }
+}
+
+fn migration() -> Migration {
+    Migration {
+        now: 1_700_000_000,
+        next_id: 10,
+        steps: 0,
+        downgraded: Vec::new(),
+    }
+}
+
+#[test]
+fn test_upgrade_with_context() {
+    let mut ctx = migration();
+    let first = serde_bare::to_vec(&TodoV1 {
+        title: "first".to_string(),
+    })
+    .unwrap();
+    let second = serde_bare::to_vec(&TodoV1 {
+        title: "second".to_string(),
+    })
+    .unwrap();
+
+    let first = Todo::deserialize_with_context(&first, 1, &mut ctx).unwrap();
+    let second = Todo::deserialize_with_context(&second, 1, &mut ctx).unwrap();
+    assert_eq!(
+        (first.id, first.created_at, first.updated_at, second.id),
+        (10, 1_700_000_000, 1_700_000_000, 11)
+    );
+    assert_eq!((ctx.next_id, ctx.steps), (12, 4));
+
+    // Latest payloads do not touch the context
+    let latest = serde_bare::to_vec(&first).unwrap();
+    assert_eq!(
+        Todo::deserialize_with_context(&latest, 3, &mut ctx).unwrap(),
+        first
+    );
+    assert_eq!((ctx.next_id, ctx.steps), (12, 4));
+}
+
+#[test]
+fn test_downgrade_with_context() {
+    let mut ctx = migration();
+    let todo = TodoV3 {
+        id: 3,
+        title: "old client".to_string(),
+        created_at: 0,
+        updated_at: 0,
+    };
+
+    let payload = Todo::wrap_latest(todo.clone())
+        .serialize_with_context(2, &mut ctx)
+        .unwrap();
+    assert_eq!(serde_bare::from_slice::<TodoV2>(&payload).unwrap().id, 3);
+
+    // The shortcut goes straight to v1
+    let payload = Todo::wrap_latest(todo)
+        .serialize_with_context(1, &mut ctx)
+        .unwrap();
+    assert_eq!(
+        serde_bare::from_slice::<TodoV1>(&payload).unwrap().title,
+        "old client"
+    );
+    assert_eq!(ctx.downgraded, vec![3, 3]);
+
+    assert!(matches!(
+        Todo::wrap_latest(TodoV3 {
+            id: 4,
+            title: String::new(),
+            created_at: 0,
+            updated_at: 0,
+        })
+        .serialize_with_context(0, &mut ctx),
+        Err(vbare::Error::ZeroVersion)
+    ));
+}
+
+#[test]
+fn test_missing_context_converters() {
+    // A context without converters leaves no path from older versions
+    struct Prefix;
+
+    impl ContextOwnedVersionedData<Prefix> for Todo {}
+
+    let payload = serde_bare::to_vec(&TodoV1 {
+        title: "lost".to_string(),
+    })
+    .unwrap();
+    assert!(matches!(
+        <Todo as ContextOwnedVersionedData<Prefix>>::deserialize_with_context(
+            &payload,
+            1,
+            &mut Prefix
+        ),
+        Err(vbare::Error::NoMigrationPath { from: 1, to: 3 })
+    ));
+}
+
+#[test]
+fn test_borrowed_with_unsized_context() {
+    let mut prefix = String::from("user:");
+    let name = Name::deserialize_with_context(b"ada", 1, prefix.as_mut_str()).unwrap();
+    assert_eq!(name, "user:ada");
+
+    let payload = Name::wrap_latest(name)
+        .serialize_with_context(1, prefix.as_mut_str())
+        .unwrap();
+    assert_eq!(payload, b"ada");
+}
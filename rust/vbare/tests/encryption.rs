@@ -0,0 +1,95 @@
+mod common;
+
+use common::{message, Message, MessageV1};
+use vbare::envelope::Encrypted;
+use vbare::{Envelope, OwnedVersionedData};
+
+const OLD_KEY: [u8; 32] = [1; 32];
+const NEW_KEY: [u8; 32] = [2; 32];
+
+#[test]
+fn test_encrypt_round_trip() {
+    let keys = Encrypted::new(1, &OLD_KEY);
+    let secret = message(1, "secret");
+
+    let sealed = Message::wrap_latest(secret.clone())
+        .encrypt_with_embedded_version(2, &keys)
+        .unwrap();
+    assert_eq!(sealed[..6], [2, 0, 1, 0, 0, 0]);
+    assert!(!sealed.windows(6).any(|w| w == b"secret"));
+    assert_eq!(
+        Message::decrypt_with_embedded_version(&sealed, &keys).unwrap(),
+        secret
+    );
+
+    // Each message gets a fresh nonce
+    let again = Message::wrap_latest(secret)
+        .encrypt_with_embedded_version(2, &keys)
+        .unwrap();
+    assert_ne!(sealed, again);
+
+    let sealed = Message::wrap_latest(message(1, "dropped"))
+        .encrypt_with_embedded_version(1, &keys)
+        .unwrap();
+    let (version, payload) = keys.open(&sealed).unwrap();
+    assert_eq!(version, 1);
+    assert_eq!(
+        serde_bare::from_slice::<MessageV1>(&payload).unwrap(),
+        MessageV1 { id: 1 }
+    );
+}
+
+#[test]
+fn test_header_is_authenticated() {
+    let keys = Encrypted::new(1, &OLD_KEY);
+    let sealed = Message::wrap_latest(message(1, "secret"))
+        .encrypt_with_embedded_version(2, &keys)
+        .unwrap();
+
+    // Changing the version, nonce, ciphertext or tag fails to decrypt
+    for i in [0, 6, 30, sealed.len() - 1] {
+        let mut tampered = sealed.clone();
+        tampered[i] ^= 0x01;
+        assert!(matches!(
+            Message::decrypt_with_embedded_version(&tampered, &keys),
+            Err(vbare::Error::Decryption { key_id: 1 })
+        ));
+    }
+
+    assert!(matches!(
+        Message::decrypt_with_embedded_version(&sealed[..40], &keys),
+        Err(vbare::Error::TruncatedHeader)
+    ));
+    assert!(matches!(
+        Message::decrypt_with_embedded_version(&sealed, &Encrypted::new(1, &NEW_KEY)),
+        Err(vbare::Error::Decryption { key_id: 1 })
+    ));
+}
+
+#[test]
+fn test_key_rotation() {
+    let old = Encrypted::new(1, &OLD_KEY);
+    let sealed = Message::wrap_latest(message(1, "secret"))
+        .encrypt_with_embedded_version(2, &old)
+        .unwrap();
+
+    let rotated = Encrypted::new(2, &NEW_KEY).with_key(1, &OLD_KEY);
+    assert_eq!(rotated.key_id(), 2);
+    let latest = Message::decrypt_with_embedded_version(&sealed, &rotated).unwrap();
+
+    // Re-encrypted data uses the new key, which the old keys cannot open
+    let resealed = Message::wrap_latest(latest)
+        .encrypt_with_embedded_version(2, &rotated)
+        .unwrap();
+    assert_eq!(resealed[2..6], [2, 0, 0, 0]);
+    assert!(matches!(
+        Message::decrypt_with_embedded_version(&resealed, &old),
+        Err(vbare::Error::UnknownKey { key_id: 2 })
+    ));
+
+    // Keys are not shown in debug output
+    assert_eq!(
+        format!("{rotated:?}"),
+        "Encrypted { key_id: 2, key_ids: [1, 2] }"
+    );
+}
@@ -0,0 +1,243 @@
+mod common;
+
+use std::borrow::Cow;
+
+use anyhow::*;
+use common::{message, Message, MessageV1};
+use vbare::envelope::{Checksummed, Embedded, Magic, SchemaId, Varint};
+use vbare::{Envelope, OwnedVersionedData, VersionedData};
+
+/// Borrows the name straight from the payload.
+struct Name<'a>(&'a str);
+
+impl<'a> VersionedData<'a> for Name<'a> {
+    type Latest = &'a str;
+
+    const LATEST_VERSION: u16 = 1;
+
+    fn wrap_latest(latest: &'a str) -> Self {
+        Name(latest)
+    }
+
+    fn unwrap_latest(self) -> Result<&'a str> {
+        Ok(self.0)
+    }
+
+    fn deserialize_version(payload: &'a [u8], _version: u16) -> Result<Self> {
+        Ok(Name(std::str::from_utf8(payload)?))
+    }
+
+    fn serialize_version(self, _version: u16) -> Result<Vec<u8>> {
+        Ok(self.0.as_bytes().to_vec())
+    }
+}
+
+/// Stores the payload reversed, so opening it allocates.
+struct Reversed;
+
+impl Envelope for Reversed {
+    fn seal(&self, version: u16, payload: &[u8]) -> vbare::Result<Vec<u8>> {
+        Embedded.seal(version, &payload.iter().rev().copied().collect::<Vec<_>>())
+    }
+
+    fn open<'m>(&self, message: &'m [u8]) -> vbare::Result<(u16, Cow<'m, [u8]>)> {
+        let (version, payload) = Embedded.open(message)?;
+        std::result::Result::Ok((version, payload.iter().rev().copied().collect()))
+    }
+}
+
+/// The payload alone, with the version fixed at 1.
+struct Raw;
+
+impl Envelope for Raw {
+    fn seal(&self, _version: u16, payload: &[u8]) -> vbare::Result<Vec<u8>> {
+        std::result::Result::Ok(payload.to_vec())
+    }
+
+    fn open<'m>(&self, message: &'m [u8]) -> vbare::Result<(u16, Cow<'m, [u8]>)> {
+        std::result::Result::Ok((1, Cow::Borrowed(message)))
+    }
+}
+
+fn v1() -> Vec<u8> {
+    serde_bare::to_vec(&MessageV1 { id: 1 }).unwrap()
+}
+
+#[test]
+fn test_embedded_envelope() {
+    let sealed = Message::wrap_latest(message(1, "hi"))
+        .serialize_with_envelope(1, Embedded)
+        .unwrap();
+    assert_eq!(
+        sealed,
+        Message::wrap_latest(message(1, "hi"))
+            .serialize_with_embedded_version(1)
+            .unwrap()
+    );
+    assert_eq!(
+        Message::deserialize_with_envelope(&sealed, Embedded).unwrap(),
+        message(1, "")
+    );
+}
+
+#[test]
+fn test_magic_envelope() {
+    let envelope = Magic(b"TODO");
+    let sealed = envelope.seal(1, &v1()).unwrap();
+    assert_eq!(sealed, [&b"TODO"[..], &[1, 0], &v1()].concat());
+    assert_eq!(
+        Message::deserialize_with_envelope(&sealed, envelope).unwrap(),
+        message(1, "")
+    );
+
+    assert!(matches!(
+        Message::deserialize_with_envelope(&sealed, Magic(b"APP!")),
+        Err(vbare::Error::BadMagic)
+    ));
+    assert!(matches!(
+        Message::deserialize_with_envelope(b"TO", envelope),
+        Err(vbare::Error::TruncatedHeader)
+    ));
+    assert!(matches!(
+        Message::deserialize_with_envelope(b"TODO\x01", envelope),
+        Err(vbare::Error::TruncatedHeader)
+    ));
+}
+
+#[test]
+fn test_varint_envelope() {
+    for (version, header) in [
+        (1, &[0x01][..]),
+        (127, &[0x7f]),
+        (128, &[0x80, 0x01]),
+        (300, &[0xac, 0x02]),
+        (u16::MAX, &[0xff, 0xff, 0x03]),
+    ] {
+        let sealed = Varint.seal(version, b"payload").unwrap();
+        assert_eq!(sealed, [header, b"payload"].concat());
+
+        let (opened, payload) = Varint.open(&sealed).unwrap();
+        assert_eq!(opened, version);
+        assert_eq!(&payload[..], b"payload");
+    }
+
+    let sealed = Message::wrap_latest(message(2, "hi"))
+        .serialize_with_envelope(2, Varint)
+        .unwrap();
+    assert_eq!(sealed[0], 2);
+    assert_eq!(
+        Message::deserialize_with_envelope(&sealed, Varint).unwrap(),
+        message(2, "hi")
+    );
+
+    assert!(matches!(
+        Varint.open(&[0x80]),
+        Err(vbare::Error::TruncatedHeader)
+    ));
+    assert!(matches!(
+        Varint.open(&[0xff, 0xff, 0x04]),
+        Err(vbare::Error::InvalidVarint)
+    ));
+    assert!(matches!(
+        Varint.open(&[0x80, 0x80, 0x80, 0x01]),
+        Err(vbare::Error::InvalidVarint)
+    ));
+}
+
+#[test]
+fn test_schema_id_envelope() {
+    let sealed = Message::wrap_latest(message(3, "hi"))
+        .serialize_with_envelope(1, SchemaId(7))
+        .unwrap();
+    assert_eq!(sealed[..6], [7, 0, 0, 0, 1, 0]);
+    assert_eq!(
+        Message::deserialize_with_envelope(&sealed, SchemaId(7)).unwrap(),
+        message(3, "")
+    );
+
+    assert!(matches!(
+        Message::deserialize_with_envelope(&sealed, SchemaId(8)),
+        Err(vbare::Error::SchemaMismatch {
+            expected: 8,
+            got: 7
+        })
+    ));
+    assert!(matches!(
+        Message::deserialize_with_envelope(&sealed[..3], SchemaId(7)),
+        Err(vbare::Error::TruncatedHeader)
+    ));
+}
+
+#[test]
+fn test_borrowed_envelope() {
+    let sealed = Name("ada")
+        .serialize_with_envelope(1, Magic(b"NM"))
+        .unwrap();
+    assert_eq!(
+        Name::deserialize_with_envelope(&sealed, Magic(b"NM")).unwrap(),
+        "ada"
+    );
+
+    // Owned data can be decoded from a payload the envelope allocates, but borrowed data cannot
+    let sealed = Name("ada").serialize_with_envelope(1, Reversed).unwrap();
+    assert_eq!(
+        &sealed[2..],
+        b"ada".iter().rev().copied().collect::<Vec<_>>()
+    );
+    assert!(matches!(
+        Name::deserialize_with_envelope(&sealed, Reversed),
+        Err(vbare::Error::PayloadNotBorrowed { version: 1 })
+    ));
+
+    let sealed = Message::wrap_latest(message(4, "hi"))
+        .serialize_with_envelope(2, Reversed)
+        .unwrap();
+    assert_eq!(
+        Message::deserialize_with_envelope(&sealed, Reversed).unwrap(),
+        message(4, "hi")
+    );
+}
+
+#[test]
+fn test_checksummed_envelope() {
+    // CRC32C check value
+    let sealed = Checksummed(Raw).seal(1, b"123456789").unwrap();
+    assert_eq!(sealed[9..], 0xe306_9283u32.to_le_bytes());
+
+    let sealed = Message::wrap_latest(message(5, "hi"))
+        .serialize_with_envelope(1, Checksummed(Embedded))
+        .unwrap();
+    assert_eq!(
+        sealed[..sealed.len() - 4],
+        Message::wrap_latest(message(5, "hi"))
+            .serialize_with_embedded_version(1)
+            .unwrap()
+    );
+    assert_eq!(
+        Message::deserialize_with_envelope(&sealed, Checksummed(Embedded)).unwrap(),
+        message(5, "")
+    );
+
+    // Corruption anywhere in the header, payload or checksum is detected
+    for i in 0..sealed.len() {
+        let mut corrupted = sealed.clone();
+        corrupted[i] ^= 0x01;
+        assert!(matches!(
+            Message::deserialize_with_envelope(&corrupted, Checksummed(Embedded)),
+            Err(vbare::Error::ChecksumMismatch { .. })
+        ));
+    }
+
+    assert!(matches!(
+        Message::deserialize_with_envelope(&sealed[..3], Checksummed(Embedded)),
+        Err(vbare::Error::TruncatedHeader)
+    ));
+
+    // Wraps other envelopes, and borrowed data can still borrow from the message
+    let envelope = Checksummed(Magic(b"NM"));
+    let sealed = Name("ada").serialize_with_envelope(1, envelope).unwrap();
+    assert_eq!(
+        Name::deserialize_with_envelope(&sealed, envelope).unwrap(),
+        "ada"
+    );
+}
@@ -0,0 +1,144 @@
+mod common;
+
+use common::{message, Message, MessageV1};
+use vbare::{
+    framing::{parse_frame, FrameDecoder, FrameEncoder},
+    OwnedVersionedData,
+};
+
+#[test]
+fn test_frame_layout() {
+    let mut buf = Vec::new();
+    FrameEncoder::<Message>::new()
+        .encode(Message::wrap_latest(message(7, "hi")), 2, &mut buf)
+        .unwrap();
+
+    let embedded = Message::wrap_latest(message(7, "hi"))
+        .serialize_with_embedded_version(2)
+        .unwrap();
+    assert_eq!(&buf[..4], &(embedded.len() as u32).to_le_bytes());
+    assert_eq!(&buf[4..], &embedded[..]);
+
+    let frame = parse_frame(&buf, usize::MAX).unwrap().unwrap();
+    assert_eq!(frame.version, 2);
+    assert_eq!(frame.payload, &embedded[2..]);
+    assert_eq!(frame.len, buf.len());
+}
+
+#[test]
+fn test_decode_partial_reads() {
+    let encoder = FrameEncoder::<Message>::new();
+    let mut buf = Vec::new();
+    encoder
+        .encode(Message::wrap_latest(message(1, "first")), 2, &mut buf)
+        .unwrap();
+    encoder
+        .encode(Message::wrap_latest(message(2, "second")), 1, &mut buf)
+        .unwrap();
+    encoder
+        .encode(Message::wrap_latest(message(3, "third")), 2, &mut buf)
+        .unwrap();
+
+    // Feed one byte at a time to exercise every partial read boundary
+    let mut decoder = FrameDecoder::<Message>::new();
+    let mut decoded = Vec::new();
+    for byte in &buf {
+        decoder.extend(std::slice::from_ref(byte));
+        while let Some(msg) = decoder.decode().unwrap() {
+            decoded.push(msg);
+        }
+    }
+
+    assert_eq!(
+        decoded,
+        vec![message(1, "first"), message(2, ""), message(3, "third")]
+    );
+    assert_eq!(decoder.buffered_len(), 0);
+}
+
+#[test]
+fn test_decode_multiple_frames_in_one_read() {
+    let encoder = FrameEncoder::<Message>::new();
+    let mut buf = Vec::new();
+    encoder
+        .encode(Message::V1(MessageV1 { id: 1 }), 1, &mut buf)
+        .unwrap();
+    encoder
+        .encode(Message::wrap_latest(message(2, "b")), 2, &mut buf)
+        .unwrap();
+    // Trailing partial frame
+    buf.extend_from_slice(&[9, 0]);
+
+    let mut decoder = FrameDecoder::<Message>::new();
+    decoder.extend(&buf);
+    assert_eq!(decoder.decode().unwrap(), Some(message(1, "")));
+    assert_eq!(decoder.decode().unwrap(), Some(message(2, "b")));
+    assert_eq!(decoder.decode().unwrap(), None);
+    assert_eq!(decoder.buffered_len(), 2);
+}
+
+#[test]
+fn test_decode_error_skips_frame() {
+    let mut buf = Vec::new();
+    vbare::framing::write_frame(2, &[0xff], usize::MAX, &mut buf).unwrap();
+    FrameEncoder::<Message>::new()
+        .encode(Message::wrap_latest(message(3, "ok")), 2, &mut buf)
+        .unwrap();
+
+    let mut decoder = FrameDecoder::<Message>::new();
+    decoder.extend(&buf);
+    assert!(matches!(
+        decoder.decode(),
+        Err(vbare::Error::Decode { version: 2, .. })
+    ));
+    assert_eq!(decoder.decode().unwrap(), Some(message(3, "ok")));
+}
+
+#[test]
+fn test_frame_too_large() {
+    let mut buf = Vec::new();
+    let err = FrameEncoder::<Message>::with_max_frame_length(4)
+        .encode(Message::wrap_latest(message(1, "too long")), 2, &mut buf)
+        .unwrap_err();
+    assert!(matches!(err, vbare::Error::FrameTooLarge { max: 4, .. }));
+    assert!(buf.is_empty());
+
+    FrameEncoder::<Message>::new()
+        .encode(Message::wrap_latest(message(1, "too long")), 2, &mut buf)
+        .unwrap();
+    let mut decoder = FrameDecoder::<Message>::with_max_frame_length(4);
+    decoder.extend(&buf[..4]);
+    assert!(matches!(
+        decoder.decode(),
+        Err(vbare::Error::FrameTooLarge { max: 4, .. })
+    ));
+}
+
+#[test]
+fn test_frame_shorter_than_version() {
+    let mut decoder = FrameDecoder::<Message>::new();
+    decoder.extend(&[1, 0, 0, 0, 2]);
+    assert!(matches!(
+        decoder.decode(),
+        Err(vbare::Error::TruncatedHeader)
+    ));
+    assert_eq!(decoder.buffered_len(), 0);
+
+    // A short frame is skipped once all of it has arrived, and the next frame still decodes
+    let mut buf = vec![0, 0, 0, 0, 1, 0, 0, 0, 7];
+    FrameEncoder::<Message>::new()
+        .encode(Message::wrap_latest(message(3, "ok")), 2, &mut buf)
+        .unwrap();
+    decoder.extend(&buf[..6]);
+    assert!(matches!(
+        decoder.decode(),
+        Err(vbare::Error::TruncatedHeader)
+    ));
+    assert_eq!(decoder.decode().unwrap(), None);
+    decoder.extend(&buf[6..]);
+    assert!(matches!(
+        decoder.decode(),
+        Err(vbare::Error::TruncatedHeader)
+    ));
+    assert_eq!(decoder.decode().unwrap(), Some(message(3, "ok")));
+}
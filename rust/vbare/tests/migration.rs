@@ -0,0 +1,339 @@
+use anyhow::*;
+use vbare::{Converter, DeserializeInfo, MigrationStep, OwnedVersionedData, Shortcut};
+
+const LINEAR: u8 = 0;
+const SHORTCUTS: u8 = 1;
+const INVALID: u8 = 2;
+const GAP: u8 = 3;
+const MISSING: u8 = 4;
+const EXTRA: u8 = 5;
+
+/// Five versions of a list of the versions the data has been converted through, so tests can
+/// check which converters ran. `S` selects the set of shortcuts.
+#[derive(Debug, Clone, PartialEq)]
+struct Trail<const S: u8> {
+    version: u16,
+    visited: Vec<u16>,
+}
+
+impl<const S: u8> Trail<S> {
+    fn step(self, from: u16, to: u16) -> Result<Self> {
+        ensure!(
+            self.version == from,
+            "expected version {from}, got {}",
+            self.version
+        );
+        let mut visited = self.visited;
+        visited.push(to);
+        Ok(Trail {
+            version: to,
+            visited,
+        })
+    }
+}
+
+impl<const S: u8> OwnedVersionedData for Trail<S> {
+    type Latest = Vec<u16>;
+
+    const LATEST_VERSION: u16 = 5;
+
+    fn wrap_latest(latest: Vec<u16>) -> Self {
+        Trail {
+            version: 5,
+            visited: latest,
+        }
+    }
+
+    fn unwrap_latest(self) -> Result<Vec<u16>> {
+        ensure!(self.version == 5, "version not latest");
+        Ok(self.visited)
+    }
+
+    fn deserialize_version(payload: &[u8], version: u16) -> Result<Self> {
+        Ok(Trail {
+            version,
+            visited: serde_bare::from_slice(payload)?,
+        })
+    }
+
+    fn serialize_version(self, version: u16) -> Result<Vec<u8>> {
+        ensure!(self.version == version, "expected version {version}");
+        serde_bare::to_vec(&self.visited).map_err(Into::into)
+    }
+
+    fn deserialize_converters() -> &'static [Converter<Self>] {
+        match S {
+            GAP | MISSING => &[|t| t.step(3, 4), |t| t.step(4, 5)],
+            EXTRA => &[
+                |t| t.step(0, 1),
+                |t| t.step(1, 2),
+                |t| t.step(2, 3),
+                |t| t.step(3, 4),
+                |t| t.step(4, 5),
+            ],
+            _ => &[
+                |t| t.step(1, 2),
+                |t| t.step(2, 3),
+                |t| t.step(3, 4),
+                |t| t.step(4, 5),
+            ],
+        }
+    }
+
+    fn serialize_converters() -> &'static [Converter<Self>] {
+        &[
+            |t| t.step(5, 4),
+            |t| t.step(4, 3),
+            |t| t.step(3, 2),
+            |t| t.step(2, 1),
+        ]
+    }
+
+    fn deserialize_shortcuts() -> &'static [Shortcut<Self>] {
+        match S {
+            SHORTCUTS => &[
+                Shortcut {
+                    from: 1,
+                    to: 3,
+                    convert: |t| t.step(1, 3),
+                },
+                Shortcut {
+                    from: 2,
+                    to: 5,
+                    convert: |t| t.step(2, 5),
+                },
+            ],
+            INVALID => &[Shortcut {
+                from: 3,
+                to: 2,
+                convert: |t| t.step(3, 2),
+            }],
+            GAP => &[Shortcut {
+                from: 2,
+                to: 4,
+                convert: |t| t.step(2, 4),
+            }],
+            _ => &[],
+        }
+    }
+
+    fn serialize_shortcuts() -> &'static [Shortcut<Self>] {
+        match S {
+            SHORTCUTS => &[Shortcut {
+                from: 5,
+                to: 2,
+                convert: |t| t.step(5, 2),
+            }],
+            _ => &[],
+        }
+    }
+}
+
+fn steps(steps: &[(u16, u16)]) -> Vec<MigrationStep> {
+    steps
+        .iter()
+        .map(|&(from, to)| MigrationStep { from, to })
+        .collect()
+}
+
+fn payload(visited: &[u16]) -> Vec<u8> {
+    serde_bare::to_vec(&visited.to_vec()).unwrap()
+}
+
+#[test]
+fn test_linear_path() {
+    assert_eq!(
+        Trail::<LINEAR>::upgrade_path(1).unwrap(),
+        steps(&[(1, 2), (2, 3), (3, 4), (4, 5)])
+    );
+    assert_eq!(
+        Trail::<LINEAR>::downgrade_path(2).unwrap(),
+        steps(&[(5, 4), (4, 3), (3, 2)])
+    );
+    assert_eq!(Trail::<LINEAR>::upgrade_path(5).unwrap(), steps(&[]));
+
+    assert_eq!(
+        Trail::<LINEAR>::deserialize(&payload(&[1]), 1).unwrap(),
+        vec![1, 2, 3, 4, 5]
+    );
+}
+
+#[test]
+fn test_upgrade_takes_shortest_path() {
+    // 1 -> 2 -> 5 beats both 1 -> 3 -> 4 -> 5 and the adjacent chain
+    assert_eq!(
+        Trail::<SHORTCUTS>::upgrade_path(1).unwrap(),
+        steps(&[(1, 2), (2, 5)])
+    );
+    assert_eq!(
+        Trail::<SHORTCUTS>::deserialize(&payload(&[1]), 1).unwrap(),
+        vec![1, 2, 5]
+    );
+
+    // Shortcuts that start before the version are not used
+    assert_eq!(
+        Trail::<SHORTCUTS>::upgrade_path(3).unwrap(),
+        steps(&[(3, 4), (4, 5)])
+    );
+    assert_eq!(
+        Trail::<SHORTCUTS>::deserialize(&payload(&[3]), 3).unwrap(),
+        vec![3, 4, 5]
+    );
+}
+
+#[test]
+fn test_downgrade_takes_shortest_path() {
+    assert_eq!(
+        Trail::<SHORTCUTS>::downgrade_path(1).unwrap(),
+        steps(&[(5, 2), (2, 1)])
+    );
+    let bytes = Trail::<SHORTCUTS>::wrap_latest(vec![5])
+        .serialize(1)
+        .unwrap();
+    assert_eq!(bytes, payload(&[5, 2, 1]));
+
+    // Shortcuts that overshoot the version are not used
+    assert_eq!(
+        Trail::<SHORTCUTS>::downgrade_path(3).unwrap(),
+        steps(&[(5, 4), (4, 3)])
+    );
+}
+
+#[test]
+fn test_shortcut_bridges_missing_converters() {
+    assert_eq!(
+        Trail::<GAP>::upgrade_path(2).unwrap(),
+        steps(&[(2, 4), (4, 5)])
+    );
+    assert_eq!(
+        Trail::<GAP>::deserialize(&payload(&[2]), 2).unwrap(),
+        vec![2, 4, 5]
+    );
+
+    assert!(matches!(
+        Trail::<GAP>::upgrade_path(1),
+        Err(vbare::Error::NoMigrationPath { from: 1, to: 5 })
+    ));
+    assert!(matches!(
+        Trail::<GAP>::deserialize(&payload(&[1]), 1),
+        Err(vbare::Error::NoMigrationPath { from: 1, to: 5 })
+    ));
+}
+
+#[test]
+fn test_missing_converters() {
+    // Versions are still supported, but there is no converter to migrate them with
+    assert_eq!(
+        Trail::<MISSING>::supported_versions(),
+        vbare::VersionRange::new(1, 5).unwrap()
+    );
+    assert!(matches!(
+        Trail::<MISSING>::deserialize(&payload(&[1]), 1),
+        Err(vbare::Error::NoMigrationPath { from: 1, to: 5 })
+    ));
+    assert!(matches!(
+        Trail::<MISSING>::upgrade_path(2),
+        Err(vbare::Error::NoMigrationPath { from: 2, to: 5 })
+    ));
+    assert_eq!(
+        Trail::<MISSING>::deserialize(&payload(&[3]), 3).unwrap(),
+        vec![3, 4, 5]
+    );
+
+    let err = Trail::<EXTRA>::deserialize(&payload(&[5]), 5).unwrap_err();
+    assert!(matches!(
+        err,
+        vbare::Error::TooManyConverters { latest: 5, got: 5 }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "5 converters given for latest version (5), expected at most 4"
+    );
+}
+
+#[test]
+fn test_invalid_shortcut() {
+    assert!(matches!(
+        Trail::<INVALID>::upgrade_path(1),
+        Err(vbare::Error::InvalidShortcut { from: 3, to: 2 })
+    ));
+    assert!(matches!(
+        Trail::<INVALID>::deserialize(&payload(&[1]), 1),
+        Err(vbare::Error::InvalidShortcut { from: 3, to: 2 })
+    ));
+}
+
+#[test]
+fn test_deserialize_info() {
+    let (latest, info) = Trail::<SHORTCUTS>::deserialize_with_info(&payload(&[1]), 1).unwrap();
+    assert_eq!(latest, vec![1, 2, 5]);
+    assert_eq!(
+        info,
+        DeserializeInfo {
+            version: 1,
+            steps: 2,
+            needs_rewrite: true,
+        }
+    );
+
+    let embedded = [&5u16.to_le_bytes()[..], &payload(&[5])].concat();
+    let (latest, info) =
+        Trail::<SHORTCUTS>::deserialize_with_embedded_version_info(&embedded).unwrap();
+    assert_eq!(latest, vec![5]);
+    assert_eq!(
+        info,
+        DeserializeInfo {
+            version: 5,
+            steps: 0,
+            needs_rewrite: false,
+        }
+    );
+
+    assert!(matches!(
+        Trail::<SHORTCUTS>::deserialize_with_embedded_version_info(&[5]),
+        Err(vbare::Error::TruncatedHeader)
+    ));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_serialize_many() {
+    let payloads = Trail::<LINEAR>::wrap_latest(vec![5])
+        .serialize_many(&[2, 4, 5, 2])
+        .unwrap();
+    assert_eq!(payloads.len(), 3);
+    assert_eq!(payloads[&5], payload(&[5]));
+    assert_eq!(payloads[&4], payload(&[5, 4]));
+    assert_eq!(payloads[&2], payload(&[5, 4, 3, 2]));
+
+    // Versions on the path to the oldest one are encoded on the way
+    let payloads = Trail::<SHORTCUTS>::wrap_latest(vec![5])
+        .serialize_many(&[1, 2])
+        .unwrap();
+    assert_eq!(payloads[&2], payload(&[5, 2]));
+    assert_eq!(payloads[&1], payload(&[5, 2, 1]));
+
+    // Versions the path skips are serialized on their own, so every payload matches `serialize`
+    let payloads = Trail::<SHORTCUTS>::wrap_latest(vec![5])
+        .serialize_many(&[1, 4])
+        .unwrap();
+    assert_eq!(payloads[&1], payload(&[5, 2, 1]));
+    assert_eq!(payloads[&4], payload(&[5, 4]));
+    for version in [1, 4] {
+        assert_eq!(
+            payloads[&version],
+            Trail::<SHORTCUTS>::wrap_latest(vec![5])
+                .serialize(version)
+                .unwrap()
+        );
+    }
+
+    assert!(Trail::<LINEAR>::wrap_latest(vec![5])
+        .serialize_many(&[])
+        .unwrap()
+        .is_empty());
+    assert!(matches!(
+        Trail::<LINEAR>::wrap_latest(vec![5]).serialize_many(&[1, 6]),
+        Err(vbare::Error::UnsupportedVersion { got: 6, latest: 5 })
+    ));
+}
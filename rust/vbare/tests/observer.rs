@@ -0,0 +1,293 @@
+use std::sync::{Mutex, Once};
+use std::time::Duration;
+
+use anyhow::*;
+use vbare::{
+    AsyncConverter, AsyncOwnedVersionedData, ContextConverter, ContextOwnedVersionedData,
+    Converter, DowngradeReport, MigrationObserver, MigrationStep, OwnedVersionedData,
+    ReportingOwnedVersionedData,
+};
+
+/// Three versions of a counter that is incremented by every converter. `T` gives each test its own
+/// type name, since the observer is shared by the whole test binary.
+#[derive(Debug, Clone, PartialEq)]
+struct Counter<const T: u8> {
+    version: u16,
+    count: u32,
+}
+
+impl<const T: u8> Counter<T> {
+    fn step(self, to: u16) -> Result<Self> {
+        ensure!(self.count != 13, "unlucky count");
+        Ok(Counter {
+            version: to,
+            count: self.count + 1,
+        })
+    }
+}
+
+impl<const T: u8> OwnedVersionedData for Counter<T> {
+    type Latest = u32;
+
+    const LATEST_VERSION: u16 = 3;
+
+    fn wrap_latest(latest: u32) -> Self {
+        Counter {
+            version: 3,
+            count: latest,
+        }
+    }
+
+    fn unwrap_latest(self) -> Result<u32> {
+        ensure!(self.version == 3, "version not latest");
+        Ok(self.count)
+    }
+
+    fn deserialize_version(payload: &[u8], version: u16) -> Result<Self> {
+        Ok(Counter {
+            version,
+            count: serde_bare::from_slice(payload)?,
+        })
+    }
+
+    fn serialize_version(self, _version: u16) -> Result<Vec<u8>> {
+        serde_bare::to_vec(&self.count).map_err(Into::into)
+    }
+
+    fn deserialize_converters() -> &'static [Converter<Self>] {
+        &[|c| c.step(2), |c| c.step(3)]
+    }
+
+    fn serialize_converters() -> &'static [Converter<Self>] {
+        &[|c| c.step(2), |c| c.step(1)]
+    }
+}
+
+impl<const T: u8> ContextOwnedVersionedData<()> for Counter<T> {
+    fn deserialize_context_converters() -> &'static [ContextConverter<Self, ()>] {
+        &[|c, _| c.step(2), |c, _| c.step(3)]
+    }
+
+    fn serialize_context_converters() -> &'static [ContextConverter<Self, ()>] {
+        &[|c, _| c.step(2), |c, _| c.step(1)]
+    }
+}
+
+impl<const T: u8> ContextOwnedVersionedData<DowngradeReport> for Counter<T> {
+    fn serialize_context_converters() -> &'static [ContextConverter<Self, DowngradeReport>] {
+        &[|c, _| c.step(2), |c, _| c.step(1)]
+    }
+}
+
+impl<const T: u8> AsyncOwnedVersionedData<()> for Counter<T> {
+    fn deserialize_async_converters() -> &'static [AsyncConverter<Self, ()>] {
+        &[
+            |c, _| Box::pin(async move { c.step(2) }),
+            |c, _| Box::pin(async move { c.step(3) }),
+        ]
+    }
+
+    fn serialize_async_converters() -> &'static [AsyncConverter<Self, ()>] {
+        &[
+            |c, _| Box::pin(async move { c.step(2) }),
+            |c, _| Box::pin(async move { c.step(1) }),
+        ]
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Event {
+    Deserialize(u16),
+    Serialize(u16),
+    Step(MigrationStep),
+    StepError(MigrationStep, String),
+}
+
+struct Recorder {
+    events: Mutex<Vec<(&'static str, Event)>>,
+}
+
+impl Recorder {
+    fn record(&self, type_name: &'static str, event: Event) {
+        self.events.lock().unwrap().push((type_name, event));
+    }
+
+    /// Returns the events recorded for `T`.
+    fn events<T>(&self) -> Vec<Event> {
+        let type_name = std::any::type_name::<T>();
+        let mut events = self.events.lock().unwrap();
+        let (matching, rest) = events.drain(..).partition(|(name, _)| *name == type_name);
+        *events = rest;
+        matching.into_iter().map(|(_, event)| event).collect()
+    }
+}
+
+impl MigrationObserver for Recorder {
+    fn on_deserialize(&self, type_name: &'static str, version: u16) {
+        self.record(type_name, Event::Deserialize(version));
+    }
+
+    fn on_serialize(&self, type_name: &'static str, version: u16) {
+        self.record(type_name, Event::Serialize(version));
+    }
+
+    fn on_step(&self, type_name: &'static str, step: MigrationStep, _elapsed: Duration) {
+        self.record(type_name, Event::Step(step));
+    }
+
+    fn on_step_error(&self, type_name: &'static str, step: MigrationStep, error: &Error) {
+        self.record(type_name, Event::StepError(step, error.to_string()));
+    }
+}
+
+static RECORDER: Recorder = Recorder {
+    events: Mutex::new(Vec::new()),
+};
+
+fn recorder() -> &'static Recorder {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| vbare::set_observer(&RECORDER).unwrap());
+    &RECORDER
+}
+
+fn step(from: u16, to: u16) -> MigrationStep {
+    MigrationStep { from, to }
+}
+
+#[test]
+fn test_observer_sees_steps() {
+    let recorder = recorder();
+
+    let payload = serde_bare::to_vec(&1u32).unwrap();
+    assert_eq!(Counter::<0>::deserialize(&payload, 1).unwrap(), 3);
+    assert_eq!(Counter::<0>::deserialize(&payload, 3).unwrap(), 1);
+    Counter::<0>::wrap_latest(1).serialize(2).unwrap();
+
+    assert_eq!(
+        recorder.events::<Counter<0>>(),
+        vec![
+            Event::Deserialize(1),
+            Event::Step(step(1, 2)),
+            Event::Step(step(2, 3)),
+            Event::Deserialize(3),
+            Event::Serialize(2),
+            Event::Step(step(3, 2)),
+        ]
+    );
+}
+
+#[test]
+fn test_observer_sees_failures() {
+    let recorder = recorder();
+
+    let payload = serde_bare::to_vec(&12u32).unwrap();
+    assert!(matches!(
+        Counter::<1>::deserialize(&payload, 1),
+        Err(vbare::Error::Convert { from: 2, to: 3, .. })
+    ));
+
+    // Unsupported versions are still counted
+    assert!(Counter::<1>::deserialize(&payload, 4).is_err());
+
+    assert_eq!(
+        recorder.events::<Counter<1>>(),
+        vec![
+            Event::Deserialize(1),
+            Event::Step(step(1, 2)),
+            Event::StepError(step(2, 3), "unlucky count".to_string()),
+            Event::Deserialize(4),
+        ]
+    );
+}
+
+#[test]
+fn test_observer_sees_context_steps() {
+    let recorder = recorder();
+
+    let payload = serde_bare::to_vec(&1u32).unwrap();
+    assert_eq!(
+        Counter::<2>::deserialize_with_context(&payload, 1, &mut ()).unwrap(),
+        3
+    );
+    Counter::<2>::wrap_latest(1)
+        .serialize_with_context(2, &mut ())
+        .unwrap();
+
+    let payload = serde_bare::to_vec(&12u32).unwrap();
+    assert!(Counter::<2>::deserialize_with_context(&payload, 1, &mut ()).is_err());
+
+    assert_eq!(
+        recorder.events::<Counter<2>>(),
+        vec![
+            Event::Deserialize(1),
+            Event::Step(step(1, 2)),
+            Event::Step(step(2, 3)),
+            Event::Serialize(2),
+            Event::Step(step(3, 2)),
+            Event::Deserialize(1),
+            Event::Step(step(1, 2)),
+            Event::StepError(step(2, 3), "unlucky count".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_observer_sees_report_steps() {
+    let recorder = recorder();
+
+    Counter::<3>::wrap_latest(1)
+        .serialize_with_report(1, &mut DowngradeReport::new())
+        .unwrap();
+    Counter::<3>::wrap_latest(1)
+        .serialize_strict(2, &mut DowngradeReport::new())
+        .unwrap();
+
+    assert_eq!(
+        recorder.events::<Counter<3>>(),
+        vec![
+            Event::Serialize(1),
+            Event::Step(step(3, 2)),
+            Event::Step(step(2, 1)),
+            Event::Serialize(2),
+            Event::Step(step(3, 2)),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_observer_sees_async_steps() {
+    let recorder = recorder();
+
+    let payload = serde_bare::to_vec(&1u32).unwrap();
+    assert_eq!(
+        Counter::<4>::deserialize_async(&payload, 1, &())
+            .await
+            .unwrap(),
+        3
+    );
+    Counter::<4>::wrap_latest(12)
+        .serialize_async(1, &())
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        recorder.events::<Counter<4>>(),
+        vec![
+            Event::Deserialize(1),
+            Event::Step(step(1, 2)),
+            Event::Step(step(2, 3)),
+            Event::Serialize(1),
+            Event::Step(step(3, 2)),
+            Event::StepError(step(2, 1), "unlucky count".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_set_observer_twice() {
+    recorder();
+    assert!(matches!(
+        vbare::set_observer(&RECORDER),
+        Err(vbare::Error::ObserverAlreadySet)
+    ));
+}
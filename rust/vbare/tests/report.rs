@@ -0,0 +1,309 @@
+use anyhow::*;
+use serde::{Deserialize, Serialize};
+use vbare::{
+    ContextConverter, ContextOwnedVersionedData, ContextShortcut, DowngradeReport, Loss,
+    OwnedVersionedData, ReportingOwnedVersionedData,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TodoV1 {
+    title: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TodoV2 {
+    title: String,
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TodoV3 {
+    title: String,
+    tags: Vec<String>,
+    priority: Option<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Todo {
+    V1(TodoV1),
+    V2(TodoV2),
+    V3(TodoV3),
+}
+
+impl OwnedVersionedData for Todo {
+    type Latest = TodoV3;
+
+    const LATEST_VERSION: u16 = 3;
+
+    fn wrap_latest(latest: TodoV3) -> Self {
+        Todo::V3(latest)
+    }
+
+    fn unwrap_latest(self) -> Result<Self::Latest> {
+        match self {
+            Todo::V3(data) => Ok(data),
+            _ => bail!("version not latest"),
+        }
+    }
+
+    fn deserialize_version(payload: &[u8], version: u16) -> Result<Self> {
+        match version {
+            1 => Ok(Todo::V1(serde_bare::from_slice(payload)?)),
+            2 => Ok(Todo::V2(serde_bare::from_slice(payload)?)),
+            3 => Ok(Todo::V3(serde_bare::from_slice(payload)?)),
+            _ => bail!("invalid version: {version}"),
+        }
+    }
+
+    fn serialize_version(self, _version: u16) -> Result<Vec<u8>> {
+        match self {
+            Todo::V1(data) => serde_bare::to_vec(&data).map_err(Into::into),
+            Todo::V2(data) => serde_bare::to_vec(&data).map_err(Into::into),
+            Todo::V3(data) => serde_bare::to_vec(&data).map_err(Into::into),
+        }
+    }
+}
+
+impl ContextOwnedVersionedData<DowngradeReport> for Todo {
+    fn serialize_context_converters() -> &'static [ContextConverter<Self, DowngradeReport>] {
+        &[
+            |todo, report| match todo {
+                Todo::V3(v3) => {
+                    if let Some(priority) = v3.priority {
+                        report.record("priority", format!("priority {priority} dropped"));
+                    }
+                    Ok(Todo::V2(TodoV2 {
+                        title: v3.title,
+                        tags: v3.tags,
+                    }))
+                }
+                other => Ok(other),
+            },
+            |todo, report| match todo {
+                Todo::V2(v2) => {
+                    for (i, tag) in v2.tags.iter().enumerate() {
+                        report.record(format!("tags[{i}]"), format!("tag {tag:?} dropped"));
+                    }
+                    Ok(Todo::V1(TodoV1 { title: v2.title }))
+                }
+                other => Ok(other),
+            },
+        ]
+    }
+}
+
+/// Context that keeps a report alongside other state the converters need.
+#[derive(Default)]
+struct Session {
+    report: DowngradeReport,
+    steps: u32,
+}
+
+impl AsMut<DowngradeReport> for Session {
+    fn as_mut(&mut self) -> &mut DowngradeReport {
+        &mut self.report
+    }
+}
+
+impl ContextOwnedVersionedData<Session> for Todo {
+    fn serialize_context_converters() -> &'static [ContextConverter<Self, Session>] {
+        &[|todo, session| {
+            session.steps += 1;
+            match todo {
+                Todo::V3(v3) => {
+                    if v3.priority.is_some() {
+                        session.report.record("priority", "priority dropped");
+                    }
+                    Ok(Todo::V2(TodoV2 {
+                        title: v3.title,
+                        tags: v3.tags,
+                    }))
+                }
+                other => Ok(other),
+            }
+        }]
+    }
+}
+
+/// Same as `Todo`, but downgrades straight from v3 to v1.
+#[derive(Debug, Clone, PartialEq)]
+struct Shortcut(Todo);
+
+impl OwnedVersionedData for Shortcut {
+    type Latest = TodoV3;
+
+    const LATEST_VERSION: u16 = 3;
+
+    fn wrap_latest(latest: TodoV3) -> Self {
+        Shortcut(Todo::wrap_latest(latest))
+    }
+
+    fn unwrap_latest(self) -> Result<Self::Latest> {
+        self.0.unwrap_latest()
+    }
+
+    fn deserialize_version(payload: &[u8], version: u16) -> Result<Self> {
+        Todo::deserialize_version(payload, version).map(Shortcut)
+    }
+
+    fn serialize_version(self, version: u16) -> Result<Vec<u8>> {
+        self.0.serialize_version(version)
+    }
+}
+
+impl ContextOwnedVersionedData<DowngradeReport> for Shortcut {
+    fn serialize_context_converters() -> &'static [ContextConverter<Self, DowngradeReport>] {
+        &[|_, _| bail!("adjacent converter used")]
+    }
+
+    fn serialize_context_shortcuts() -> &'static [ContextShortcut<Self, DowngradeReport>] {
+        &[ContextShortcut {
+            from: 3,
+            to: 1,
+            convert: |todo, report| match todo.0 {
+                Todo::V3(v3) => {
+                    report.record("tags", "tags and priority dropped");
+                    Ok(Shortcut(Todo::V1(TodoV1 { title: v3.title })))
+                }
+                other => Ok(Shortcut(other)),
+            },
+        }]
+    }
+}
+
+fn todo(tags: &[&str], priority: Option<u8>) -> Todo {
+    Todo::wrap_latest(TodoV3 {
+        title: "ship".to_string(),
+        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        priority,
+    })
+}
+
+#[test]
+fn test_report_records_losses() {
+    let (payload, report) = todo(&["work"], Some(1))
+        .serialize_with_report(1, &mut DowngradeReport::new())
+        .unwrap();
+    assert_eq!(
+        serde_bare::from_slice::<TodoV1>(&payload).unwrap(),
+        TodoV1 {
+            title: "ship".to_string()
+        }
+    );
+
+    assert!(!report.is_lossless());
+    assert_eq!(
+        report.losses(),
+        &[
+            Loss {
+                from: 3,
+                to: 2,
+                path: "priority".into(),
+                reason: "priority 1 dropped".into(),
+            },
+            Loss {
+                from: 2,
+                to: 1,
+                path: "tags[0]".into(),
+                reason: "tag \"work\" dropped".into(),
+            },
+        ]
+    );
+
+    // Only the steps down to the requested version run
+    let (_, report) = todo(&["work"], Some(1))
+        .serialize_with_report(2, &mut DowngradeReport::new())
+        .unwrap();
+    assert_eq!(report.losses().len(), 1);
+}
+
+#[test]
+fn test_report_lossless() {
+    let (payload, report) = todo(&[], None)
+        .serialize_with_report(1, &mut DowngradeReport::new())
+        .unwrap();
+    assert!(report.is_lossless());
+    assert_eq!(
+        todo(&[], None)
+            .serialize_strict(1, &mut DowngradeReport::new())
+            .unwrap(),
+        payload,
+        "strict serialize succeeds when nothing is lost"
+    );
+
+    let (_, report) = todo(&["work"], Some(1))
+        .serialize_with_report(3, &mut DowngradeReport::new())
+        .unwrap();
+    assert!(report.is_lossless());
+}
+
+#[test]
+fn test_strict_rejects_loss() {
+    let err = todo(&["work", "home"], None)
+        .serialize_strict(1, &mut DowngradeReport::new())
+        .unwrap_err();
+    let vbare::Error::LossyDowngrade(loss) = &err else {
+        panic!("expected lossy downgrade, got {err:?}");
+    };
+    // The first loss of the step is reported
+    assert_eq!((loss.from, loss.to), (2, 1));
+    assert_eq!(
+        err.to_string(),
+        "downgrading version 2 to version 1 loses tags[0]: tag \"work\" dropped"
+    );
+
+    assert!(todo(&["work"], None)
+        .serialize_strict(2, &mut DowngradeReport::new())
+        .is_ok());
+    assert!(matches!(
+        todo(&[], None).serialize_strict(4, &mut DowngradeReport::new()),
+        Err(vbare::Error::UnsupportedVersion { got: 4, latest: 3 })
+    ));
+}
+
+#[test]
+fn test_report_in_own_context() {
+    let mut session = Session::default();
+    session.report.record("earlier", "recorded by the caller");
+
+    let (_, report) = todo(&[], Some(1))
+        .serialize_with_report(2, &mut session)
+        .unwrap();
+    assert_eq!(session.steps, 1);
+    assert_eq!(
+        report.losses(),
+        &[Loss {
+            from: 3,
+            to: 2,
+            path: "priority".into(),
+            reason: "priority dropped".into(),
+        }]
+    );
+
+    // Losses from before the call stay in the context
+    assert_eq!(session.report.losses().len(), 1);
+    assert_eq!(session.report.losses()[0].path, "earlier");
+
+    assert!(matches!(
+        todo(&[], Some(1)).serialize_strict(2, &mut session),
+        Err(vbare::Error::LossyDowngrade(Loss { from: 3, to: 2, .. }))
+    ));
+    assert_eq!(session.report.losses().len(), 1);
+}
+
+#[test]
+fn test_report_through_shortcut() {
+    let (_, report) = Shortcut::wrap_latest(TodoV3 {
+        title: "ship".to_string(),
+        tags: Vec::new(),
+        priority: None,
+    })
+    .serialize_with_report(1, &mut DowngradeReport::new())
+    .unwrap();
+
+    // Losses are attributed to the step that recorded them, not to adjacent versions
+    let [loss] = report.losses() else {
+        panic!("expected one loss, got {:?}", report.losses());
+    };
+    assert_eq!((loss.from, loss.to), (3, 1));
+}
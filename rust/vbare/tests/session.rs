@@ -0,0 +1,159 @@
+mod common;
+
+use std::io::{Cursor, Read, Write};
+
+use anyhow::*;
+use common::{message, Message, MessageV1};
+use vbare::{
+    session::{VersionRange, VersionedSession},
+    OwnedVersionedData,
+};
+
+/// Single-version type used to test sessions where `In` and `Out` have different latest versions.
+#[derive(Debug, Clone, PartialEq)]
+struct Ack(u32);
+
+impl OwnedVersionedData for Ack {
+    type Latest = u32;
+
+    const LATEST_VERSION: u16 = 1;
+
+    fn wrap_latest(latest: u32) -> Self {
+        Ack(latest)
+    }
+
+    fn unwrap_latest(self) -> Result<u32> {
+        Ok(self.0)
+    }
+
+    fn deserialize_version(payload: &[u8], _version: u16) -> Result<Self> {
+        Ok(Ack(serde_bare::from_slice(payload)?))
+    }
+
+    fn serialize_version(self, _version: u16) -> Result<Vec<u8>> {
+        serde_bare::to_vec(&self.0).map_err(Into::into)
+    }
+}
+
+/// Reads the remote handshake from `remote` and records everything written.
+struct Peer {
+    remote: Cursor<Vec<u8>>,
+    written: Vec<u8>,
+}
+
+impl Peer {
+    fn new(remote: VersionRange) -> Self {
+        Peer {
+            remote: Cursor::new(remote.to_bytes().to_vec()),
+            written: Vec::new(),
+        }
+    }
+}
+
+impl Read for Peer {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.remote.read(buf)
+    }
+}
+
+impl Write for Peer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.written.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        std::io::Result::Ok(())
+    }
+}
+
+#[test]
+fn test_negotiate_highest_common_version() {
+    let local = VersionRange::new(1, 3).unwrap();
+    assert_eq!(
+        local.negotiate(&VersionRange::new(2, 5).unwrap()).unwrap(),
+        3
+    );
+    assert_eq!(
+        local.negotiate(&VersionRange::new(1, 2).unwrap()).unwrap(),
+        2
+    );
+    assert_eq!(
+        local.negotiate(&VersionRange::new(3, 3).unwrap()).unwrap(),
+        3
+    );
+
+    let err = local
+        .negotiate(&VersionRange::new(4, 5).unwrap())
+        .unwrap_err();
+    assert!(matches!(err, vbare::Error::NoCommonVersion { .. }));
+    assert_eq!(
+        err.to_string(),
+        "no common version between local versions (1..=3) and remote versions (4..=5)"
+    );
+}
+
+#[test]
+fn test_invalid_version_range() {
+    assert!(matches!(
+        VersionRange::new(0, 2),
+        Err(vbare::Error::InvalidVersionRange { min: 0, max: 2 })
+    ));
+    assert!(matches!(
+        VersionRange::new(3, 2),
+        Err(vbare::Error::InvalidVersionRange { min: 3, max: 2 })
+    ));
+    assert!(matches!(
+        VersionRange::from_bytes([0, 0, 1, 0]),
+        Err(vbare::Error::InvalidVersionRange { min: 0, max: 1 })
+    ));
+}
+
+#[test]
+fn test_supported_versions() {
+    assert_eq!(
+        VersionedSession::<Message, Message>::supported_versions().unwrap(),
+        VersionRange::new(1, 2).unwrap()
+    );
+    assert_eq!(
+        VersionedSession::<Message, Ack>::supported_versions().unwrap(),
+        VersionRange::new(1, 1).unwrap()
+    );
+}
+
+#[test]
+fn test_handshake() {
+    let mut peer = Peer::new(VersionRange::new(1, 1).unwrap());
+    let session = VersionedSession::<Message, Message>::handshake(&mut peer).unwrap();
+    assert_eq!(session.version(), 1);
+    assert_eq!(peer.written, VersionRange::new(1, 2).unwrap().to_bytes());
+
+    let mut peer = Peer::new(VersionRange::new(2, 9).unwrap());
+    let session = VersionedSession::<Message, Message>::handshake(&mut peer).unwrap();
+    assert_eq!(session.version(), 2);
+}
+
+#[test]
+fn test_handshake_truncated() {
+    let mut peer = Peer {
+        remote: Cursor::new(vec![1, 0]),
+        written: Vec::new(),
+    };
+    assert!(matches!(
+        VersionedSession::<Message, Message>::handshake(&mut peer),
+        Err(vbare::Error::Io(_))
+    ));
+}
+
+#[test]
+fn test_session_encode_decode() {
+    let session = VersionedSession::<Message, Message>::new(1).unwrap();
+
+    let bytes = session.encode(message(4, "dropped")).unwrap();
+    assert_eq!(bytes, serde_bare::to_vec(&MessageV1 { id: 4 }).unwrap());
+    assert_eq!(session.decode(&bytes).unwrap(), message(4, ""));
+
+    assert!(matches!(
+        VersionedSession::<Message, Message>::new(3),
+        Err(vbare::Error::NoCommonVersion { .. })
+    ));
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use common::{message, Message};
+use futures::{SinkExt, StreamExt};
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+use vbare::session::{VersionRange, VersionedSession};
+
+#[tokio::test]
+async fn test_handshake_over_duplex() {
+    let (mut client, mut server) = tokio::io::duplex(64);
+
+    let (client_session, server_session) = tokio::join!(
+        VersionedSession::<Message, Message>::handshake_async(&mut client),
+        VersionedSession::<Message, Message>::handshake_async(&mut server),
+    );
+    let client_session = client_session.unwrap();
+    let server_session = server_session.unwrap();
+    assert_eq!(client_session.version(), 2);
+    assert_eq!(server_session.version(), 2);
+
+    let mut client = Framed::new(client, LengthDelimitedCodec::new());
+    let mut server = Framed::new(server, LengthDelimitedCodec::new());
+
+    let payload = client_session.encode(message(1, "hello")).unwrap();
+    client.send(&payload[..]).await.unwrap();
+    let frame = server.next().await.unwrap().unwrap();
+    assert_eq!(server_session.decode(&frame).unwrap(), message(1, "hello"));
+}
+
+#[tokio::test]
+async fn test_handshake_with_older_peer() {
+    let (mut client, mut server) = tokio::io::duplex(64);
+
+    // Hand-rolled peer that only speaks v1
+    let older = async {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        server
+            .write_all(&VersionRange::new(1, 1).unwrap().to_bytes())
+            .await
+            .unwrap();
+        let mut remote = [0u8; vbare::session::HANDSHAKE_LEN];
+        server.read_exact(&mut remote).await.unwrap();
+        VersionRange::from_bytes(remote).unwrap()
+    };
+
+    let (session, remote) = tokio::join!(
+        VersionedSession::<Message, Message>::handshake_async(&mut client),
+        older,
+    );
+    assert_eq!(session.unwrap().version(), 1);
+    assert_eq!(remote, VersionRange::new(1, 2).unwrap());
+}
+
+#[tokio::test]
+async fn test_handshake_no_common_version() {
+    let (mut client, mut server) = tokio::io::duplex(64);
+
+    let newer = async {
+        use tokio::io::AsyncWriteExt;
+
+        server
+            .write_all(&VersionRange::new(3, 4).unwrap().to_bytes())
+            .await
+            .unwrap();
+    };
+
+    let (session, ()) = tokio::join!(
+        VersionedSession::<Message, Message>::handshake_async(&mut client),
+        newer,
+    );
+    assert!(matches!(session, Err(vbare::Error::NoCommonVersion { .. })));
+}
@@ -1,6 +1,6 @@
 use anyhow::*;
 use serde::{Deserialize, Serialize};
-use vbare::OwnedVersionedData;
+use vbare::{Converter, OwnedVersionedData};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 struct TestDataV1 {
@@ -33,6 +33,8 @@ enum TestData {
 impl OwnedVersionedData for TestData {
     type Latest = TestDataV3;
 
+    const LATEST_VERSION: u16 = 3;
+
     fn wrap_latest(latest: TestDataV3) -> Self {
         TestData::V3(latest)
     }
@@ -63,12 +65,20 @@ impl OwnedVersionedData for TestData {
         }
     }
 
-    fn deserialize_converters() -> Vec<impl Fn(Self) -> Result<Self>> {
-        vec![Self::v1_to_v2, Self::v2_to_v3]
+    fn serialize_version_ref(&self, _version: u16) -> Option<Result<Vec<u8>>> {
+        Some(match self {
+            TestData::V1(data) => serde_bare::to_vec(data).map_err(Into::into),
+            TestData::V2(data) => serde_bare::to_vec(data).map_err(Into::into),
+            TestData::V3(data) => serde_bare::to_vec(data).map_err(Into::into),
+        })
+    }
+
+    fn deserialize_converters() -> &'static [Converter<Self>] {
+        &[Self::v1_to_v2, Self::v2_to_v3]
     }
 
-    fn serialize_converters() -> Vec<impl Fn(Self) -> Result<Self>> {
-        vec![Self::v3_to_v2, Self::v2_to_v1]
+    fn serialize_converters() -> &'static [Converter<Self>] {
+        &[Self::v3_to_v2, Self::v2_to_v1]
     }
 }
 
@@ -86,6 +96,8 @@ enum TestDataNoConverters {
 impl OwnedVersionedData for TestDataNoConverters {
     type Latest = TestDataNoConvertersV1;
 
+    const LATEST_VERSION: u16 = 1;
+
     fn wrap_latest(latest: TestDataNoConvertersV1) -> Self {
         TestDataNoConverters::V1(latest)
     }
@@ -113,6 +125,118 @@ impl OwnedVersionedData for TestDataNoConverters {
     }
 }
 
+#[derive(Clone)]
+enum TestDataFailing {
+    V1(TestDataV1),
+    V2(TestDataV2),
+}
+
+impl OwnedVersionedData for TestDataFailing {
+    type Latest = TestDataV2;
+
+    const LATEST_VERSION: u16 = 2;
+
+    fn wrap_latest(latest: TestDataV2) -> Self {
+        TestDataFailing::V2(latest)
+    }
+
+    fn unwrap_latest(self) -> Result<Self::Latest> {
+        match self {
+            TestDataFailing::V2(data) => Ok(data),
+            _ => bail!("version not latest"),
+        }
+    }
+
+    fn deserialize_version(payload: &[u8], version: u16) -> Result<Self> {
+        match version {
+            1 => Ok(TestDataFailing::V1(serde_bare::from_slice(payload)?)),
+            2 => Ok(TestDataFailing::V2(serde_bare::from_slice(payload)?)),
+            _ => bail!("invalid version: {version}"),
+        }
+    }
+
+    fn serialize_version(self, _version: u16) -> Result<Vec<u8>> {
+        match self {
+            TestDataFailing::V1(data) => serde_bare::to_vec(&data).map_err(Into::into),
+            TestDataFailing::V2(data) => serde_bare::to_vec(&data).map_err(Into::into),
+        }
+    }
+
+    fn deserialize_converters() -> &'static [Converter<Self>] {
+        &[|_| bail!("name must not be empty")]
+    }
+
+    fn serialize_converters() -> &'static [Converter<Self>] {
+        &[|_| bail!("name must not be empty")]
+    }
+}
+
+/// Same schemas as `TestData`, with version 1 sunset and its converters removed.
+#[derive(Clone)]
+enum TestDataSunset {
+    V1(TestDataV1),
+    V2(TestDataV2),
+    V3(TestDataV3),
+}
+
+impl OwnedVersionedData for TestDataSunset {
+    type Latest = TestDataV3;
+
+    const LATEST_VERSION: u16 = 3;
+    const MIN_SUPPORTED_VERSION: u16 = 2;
+
+    fn wrap_latest(latest: TestDataV3) -> Self {
+        TestDataSunset::V3(latest)
+    }
+
+    fn unwrap_latest(self) -> Result<Self::Latest> {
+        match self {
+            TestDataSunset::V3(data) => Ok(data),
+            _ => bail!("version not latest"),
+        }
+    }
+
+    fn deserialize_version(payload: &[u8], version: u16) -> Result<Self> {
+        match version {
+            1 => Ok(TestDataSunset::V1(serde_bare::from_slice(payload)?)),
+            2 => Ok(TestDataSunset::V2(serde_bare::from_slice(payload)?)),
+            3 => Ok(TestDataSunset::V3(serde_bare::from_slice(payload)?)),
+            _ => bail!("invalid version: {version}"),
+        }
+    }
+
+    fn serialize_version(self, _version: u16) -> Result<Vec<u8>> {
+        match self {
+            TestDataSunset::V1(data) => serde_bare::to_vec(&data).map_err(Into::into),
+            TestDataSunset::V2(data) => serde_bare::to_vec(&data).map_err(Into::into),
+            TestDataSunset::V3(data) => serde_bare::to_vec(&data).map_err(Into::into),
+        }
+    }
+
+    fn deserialize_converters() -> &'static [Converter<Self>] {
+        &[|data| match data {
+            TestDataSunset::V2(v2) => Ok(TestDataSunset::V3(TestDataV3 {
+                id: v2.id,
+                name: v2.name,
+                description: v2.description,
+                tags: vec![],
+            })),
+            other => Ok(other),
+        }]
+    }
+
+    fn serialize_converters() -> &'static [Converter<Self>] {
+        &[|data| match data {
+            TestDataSunset::V3(v3) => Ok(TestDataSunset::V2(TestDataV2 {
+                id: v3.id,
+                name: v3.name,
+                description: v3.description,
+            })),
+            other => Ok(other),
+        }]
+    }
+}
+
 impl TestData {
     fn v1_to_v2(self) -> Result<Self> {
         match self {
@@ -195,7 +319,126 @@ fn test_v2_to_v2() {
 
 #[test]
 fn test_unsupported_version() {
-    assert!(TestData::deserialize(&[], 99).is_err());
+    assert!(matches!(
+        TestData::deserialize(&[], 99),
+        Err(vbare::Error::UnsupportedVersion { got: 99, latest: 3 })
+    ));
+
+    let data = TestData::V1(TestDataV1 {
+        id: 1,
+        name: "test".to_string(),
+    });
+    assert!(matches!(
+        data.serialize(4),
+        Err(vbare::Error::UnsupportedVersion { got: 4, latest: 3 })
+    ));
+}
+
+#[test]
+fn test_zero_version() {
+    assert!(matches!(
+        TestData::deserialize(&[], 0),
+        Err(vbare::Error::ZeroVersion)
+    ));
+    assert!(matches!(
+        TestData::deserialize_with_embedded_version(&[0, 0]),
+        Err(vbare::Error::ZeroVersion)
+    ));
+}
+
+#[test]
+fn test_supported_versions() {
+    assert_eq!(TestData::LATEST_VERSION, 3);
+    assert_eq!(TestData::MIN_SUPPORTED_VERSION, 1);
+    assert_eq!(
+        TestData::supported_versions(),
+        vbare::VersionRange::new(1, 3).unwrap()
+    );
+    assert_eq!(
+        TestDataSunset::supported_versions(),
+        vbare::VersionRange::new(2, 3).unwrap()
+    );
+}
+
+#[test]
+fn test_version_too_old() {
+    let data = TestDataV3 {
+        id: 1,
+        name: "test".to_string(),
+        description: "kept".to_string(),
+        tags: vec!["dropped".to_string()],
+    };
+
+    let payload = TestDataSunset::wrap_latest(data.clone())
+        .serialize(2)
+        .unwrap();
+    let deserialized = TestDataSunset::deserialize(&payload, 2).unwrap();
+    assert_eq!(deserialized.description, "kept");
+    assert!(deserialized.tags.is_empty());
+
+    let payload = serde_bare::to_vec(&TestDataV1 {
+        id: 1,
+        name: "test".to_string(),
+    })
+    .unwrap();
+    let err = TestDataSunset::deserialize(&payload, 1).unwrap_err();
+    assert!(matches!(
+        err,
+        vbare::Error::VersionTooOld { got: 1, min: 2 }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "proto version (1) less than minimum supported version (2)"
+    );
+
+    assert!(matches!(
+        TestDataSunset::wrap_latest(data).serialize(1),
+        Err(vbare::Error::VersionTooOld { got: 1, min: 2 })
+    ));
+}
+
+#[test]
+fn test_truncated_header() {
+    assert!(matches!(
+        TestData::deserialize_with_embedded_version(&[1]),
+        Err(vbare::Error::TruncatedHeader)
+    ));
+}
+
+#[test]
+fn test_decode_error() {
+    let err = TestData::deserialize(&[0xff], 2).unwrap_err();
+    assert!(matches!(err, vbare::Error::Decode { version: 2, .. }));
+
+    // Interop with anyhow keeps the decode failure as the source
+    let err = anyhow::Error::from(err);
+    assert_eq!(err.to_string(), "failed to decode version 2");
+    assert_eq!(err.chain().count(), 2);
+}
+
+#[test]
+fn test_convert_error() {
+    let payload = serde_bare::to_vec(&TestDataV1 {
+        id: 1,
+        name: "".to_string(),
+    })
+    .unwrap();
+
+    let err = TestDataFailing::deserialize(&payload, 1).unwrap_err();
+    assert!(matches!(err, vbare::Error::Convert { from: 1, to: 2, .. }));
+    assert_eq!(
+        std::error::Error::source(&err).unwrap().to_string(),
+        "name must not be empty"
+    );
+
+    let err = TestDataFailing::V2(TestDataV2 {
+        id: 1,
+        name: "".to_string(),
+        description: "".to_string(),
+    })
+    .serialize(1)
+    .unwrap_err();
+    assert!(matches!(err, vbare::Error::Convert { from: 2, to: 1, .. }));
 }
 
 #[test]
@@ -262,6 +505,38 @@ fn test_v3_to_v3() {
     assert_eq!(deserialized.tags, vec!["keep".to_string()]);
 }
 
+#[test]
+fn test_serialize_ref() {
+    let data = TestData::V3(TestDataV3 {
+        id: 789,
+        name: "shared".to_string(),
+        description: "sent to everyone".to_string(),
+        tags: vec!["tag1".to_string()],
+    });
+
+    // Encoded from the borrow at the latest version, cloned for older versions
+    for version in 1..=3 {
+        assert_eq!(
+            data.serialize_ref(version).unwrap(),
+            data.clone().serialize(version).unwrap()
+        );
+    }
+    assert!(matches!(
+        data.serialize_ref(4),
+        Err(vbare::Error::UnsupportedVersion { got: 4, latest: 3 })
+    ));
+
+    // Types without `serialize_version_ref` are always cloned
+    let data = TestDataNoConverters::V1(TestDataNoConvertersV1 {
+        id: 1,
+        name: "cloned".to_string(),
+    });
+    assert_eq!(
+        data.serialize_ref(1).unwrap(),
+        data.clone().serialize(1).unwrap()
+    );
+}
+
 #[test]
 fn test_serialize() {
     let data = TestData::V3(TestDataV3 {
@@ -354,3 +629,79 @@ fn test_no_converters() {
     assert_eq!(deserialized.id, 456);
     assert_eq!(deserialized.name, "test");
 }
+
+#[cfg(feature = "std")]
+#[test]
+fn test_serialize_into() {
+    let data = TestDataV3 {
+        id: 321,
+        name: "stream".to_string(),
+        description: "into".to_string(),
+        tags: vec!["tag".to_string()],
+    };
+
+    let mut buf = Vec::new();
+    TestData::V3(data.clone())
+        .serialize_into(2, &mut buf)
+        .unwrap();
+    assert_eq!(buf, TestData::V3(data.clone()).serialize(2).unwrap());
+
+    let mut buf = Vec::new();
+    TestData::V3(data.clone())
+        .serialize_with_embedded_version_into(3, &mut buf)
+        .unwrap();
+    assert_eq!(
+        buf,
+        TestData::V3(data.clone())
+            .serialize_with_embedded_version(3)
+            .unwrap()
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_deserialize_from() {
+    let data = TestDataV2 {
+        id: 654,
+        name: "stream".to_string(),
+        description: "from".to_string(),
+    };
+
+    let payload = TestData::V2(data)
+        .serialize_with_embedded_version(2)
+        .unwrap();
+    let deserialized = TestData::deserialize_from(std::io::Cursor::new(payload)).unwrap();
+    assert_eq!(deserialized.id, 654);
+    assert_eq!(deserialized.description, "from");
+    assert_eq!(deserialized.tags.len(), 0);
+
+    assert!(matches!(
+        TestData::deserialize_from(&[1u8][..]),
+        Err(vbare::Error::TruncatedHeader)
+    ));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_serialize_into_io_error() {
+    struct FailingWriter;
+
+    impl std::io::Write for FailingWriter {
+        fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("disk full"))
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            std::io::Result::Ok(())
+        }
+    }
+
+    let data = TestData::V1(TestDataV1 {
+        id: 1,
+        name: "test".to_string(),
+    });
+    assert!(matches!(
+        data.serialize_with_embedded_version_into(1, FailingWriter),
+        Err(vbare::Error::Io(_))
+    ));
+}
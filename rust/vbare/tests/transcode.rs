@@ -0,0 +1,61 @@
+mod common;
+
+use std::borrow::Cow;
+
+use common::{message, Message, MessageV1};
+use vbare::OwnedVersionedData;
+
+#[test]
+fn test_peek_version() {
+    let payload = Message::wrap_latest(message(1, "hi"))
+        .serialize_with_embedded_version(1)
+        .unwrap();
+    assert_eq!(vbare::peek_version(&payload).unwrap(), 1);
+    assert_eq!(vbare::peek_version(&[7, 1]).unwrap(), 263);
+
+    assert!(matches!(
+        vbare::peek_version(&[1]),
+        Err(vbare::Error::TruncatedHeader)
+    ));
+}
+
+#[test]
+fn test_transcode() {
+    let v1 = serde_bare::to_vec(&MessageV1 { id: 1 }).unwrap();
+
+    // Matching versions pass the bytes through
+    let same = Message::transcode(&v1, 1, 1).unwrap();
+    assert!(matches!(same, Cow::Borrowed(bytes) if std::ptr::eq(bytes, &v1[..])));
+
+    let v2 = Message::transcode(&v1, 1, 2).unwrap();
+    assert!(matches!(v2, Cow::Owned(_)));
+    assert_eq!(Message::deserialize(&v2, 2).unwrap(), message(1, ""));
+    assert_eq!(Message::transcode(&v2, 2, 1).unwrap(), v1);
+
+    assert!(matches!(
+        Message::transcode(&v1, 3, 3),
+        Err(vbare::Error::UnsupportedVersion { got: 3, latest: 2 })
+    ));
+}
+
+#[test]
+fn test_transcode_with_embedded_version() {
+    let v2 = Message::wrap_latest(message(2, "hi"))
+        .serialize_with_embedded_version(2)
+        .unwrap();
+
+    let same = Message::transcode_with_embedded_version(&v2, 2).unwrap();
+    assert!(matches!(same, Cow::Borrowed(bytes) if std::ptr::eq(bytes, &v2[..])));
+
+    let v1 = Message::transcode_with_embedded_version(&v2, 1).unwrap();
+    assert_eq!(vbare::peek_version(&v1).unwrap(), 1);
+    assert_eq!(
+        serde_bare::from_slice::<MessageV1>(&v1[2..]).unwrap(),
+        MessageV1 { id: 2 }
+    );
+
+    assert!(matches!(
+        Message::transcode_with_embedded_version(&[2], 1),
+        Err(vbare::Error::TruncatedHeader)
+    ));
+}
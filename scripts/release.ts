@@ -400,10 +400,13 @@ async function getPublishableRustPackages(cargoTomlPaths: string[]): Promise<Pub
       continue;
     }
 
+    // Dev-dependencies are stripped on publish and may point back at dependents (e.g. vbare-derive
+    // tests depend on vbare), so they must not affect publish order.
+    const publishedSections = raw.replace(/^\[dev-dependencies\]\r?\n(?:(?!\[).*(?:\r?\n|$))*/gm, '');
     const localDependencies: string[] = [];
     const dependencyRegex = /^([A-Za-z0-9_-]+)\s*=\s*{[^}]*path\s*=\s*"([^"]+)"[^}]*}$/gm;
     let match: RegExpExecArray | null;
-    while ((match = dependencyRegex.exec(raw)) !== null) {
+    while ((match = dependencyRegex.exec(publishedSections)) !== null) {
       localDependencies.push(match[1]);
     }
 